@@ -1,6 +1,5 @@
 use bevy::{
     prelude::*,
-    render::view::RenderLayers,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
     window::WindowResolution,
 };
@@ -15,6 +14,9 @@ struct TemporaryResource;
 #[derive(Component)]
 struct RenderedEntity(Handle<Mesh>);
 
+#[derive(Resource)]
+struct DefaultTask(RenderToTextureTaskId);
+
 pub fn main() {
     let mut app = App::new();
 
@@ -38,20 +40,25 @@ pub fn main() {
 
 fn wait_for_texture(
     mut commands: Commands,
-    mut render_to_texture_tasks: ResMut<RenderToTextureTasks>,
-    mut images: ResMut<Assets<Image>>,
+    task: Res<DefaultTask>,
+    mut finished: EventReader<RenderToTextureFinished>,
+    mut failed: EventReader<RenderToTextureFailed>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     removeables: Query<Entity, With<TemporaryResource>>,
 ) {
-    if let Some(image) = render_to_texture_tasks.image("default", false) {
+    for event in failed.read().filter(|event| event.task == task.0) {
+        error!("Rendering the texture failed: {}", event.error);
+    }
+
+    for event in finished.read().filter(|event| event.task == task.0) {
         for entity in removeables.iter() {
             commands.entity(entity).despawn();
         }
 
         let m = meshes.add(Mesh::from(Plane3d::new(Vec3::new(0.0, 1.0, 0.0))));
         let mm = materials.add(StandardMaterial {
-            base_color_texture: Some(images.add(image)),
+            base_color_texture: Some(event.image.clone()),
             ..default()
         });
 
@@ -79,14 +86,12 @@ fn keyboard_input(
     keys: Res<ButtonInput<KeyCode>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut rendered: Query<&RenderedEntity>,
+    task: Res<DefaultTask>,
     mut render_to_texture_tasks: ResMut<RenderToTextureTasks>,
 ) {
     if keys.just_pressed(KeyCode::Space) {
         create_random_mesh(&mut meshes, &mut rendered);
-        render_to_texture_tasks
-            .get_mut("default")
-            .unwrap()
-            .rerender();
+        render_to_texture_tasks.get_mut(task.0).unwrap().rerender();
     }
 }
 
@@ -108,15 +113,18 @@ fn create_random_texture(
 ) {
     let mut rng = rand::thread_rng();
 
-    render_to_texture_tasks.add(
-        "default".to_string(),
-        512,
-        512,
-        true,
+    let task = render_to_texture_tasks.add(
+        Some("default"),
+        RenderToTexture {
+            size: UVec2::new(512, 512),
+            compress: true,
+            mode: RenderToTextureMode::OnDemand,
+            ..default()
+        },
         commands,
         images,
-        true,
     );
+    commands.insert_resource(DefaultTask(task));
 
     let mesh = meshes.add(RegularPolygon::new(
         rng.gen::<f32>() * 300.0,
@@ -129,7 +137,7 @@ fn create_random_texture(
             transform: Transform::from_xyz(-0.6, 0.7, 1.4),
             ..default()
         },
-        RenderLayers::layer(1),
+        render_to_texture_tasks.get(task).unwrap().get_layer(),
         RenderedEntity(mesh),
     ));
 }
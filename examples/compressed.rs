@@ -1,7 +1,6 @@
 /// Render only once to a compressed texture with mipmaps.
 use bevy::{
     prelude::*,
-    render::view::RenderLayers,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
 use render_to_texture::*;
@@ -16,20 +15,21 @@ pub fn main() {
 
 fn wait_for_texture(
     mut commands: Commands,
+    mut finished: EventReader<RenderToTextureFinished>,
     mut render_to_texture_tasks: ResMut<RenderToTextureTasks>,
-    mut images: ResMut<Assets<Image>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    if let Some(image) = render_to_texture_tasks.image("default", true) {
+    for event in finished.read() {
         commands.spawn((MaterialMeshBundle {
             mesh: meshes.add(Mesh::from(Plane3d::new(Vec3::new(0.0, 1.0, 0.0)))),
             material: materials.add(StandardMaterial {
-                base_color_texture: Some(images.add(image)),
+                base_color_texture: Some(event.image.clone()),
                 ..default()
             }),
             ..default()
         },));
+        render_to_texture_tasks.free(event.task, &mut commands);
     }
 }
 
@@ -40,14 +40,15 @@ fn setup_scene(
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut render_to_texture_tasks: ResMut<RenderToTextureTasks>,
 ) {
-    render_to_texture_tasks.add(
-        "default".to_string(),
-        512,
-        512,
-        true,
+    let task = render_to_texture_tasks.add(
+        Some("default"),
+        RenderToTexture {
+            size: UVec2::new(512, 512),
+            compress: true,
+            ..default()
+        },
         &mut commands,
         &mut images,
-        false,
     );
 
     commands.spawn((
@@ -57,7 +58,7 @@ fn setup_scene(
             transform: Transform::from_xyz(-0.6, 0.7, 1.4),
             ..default()
         },
-        RenderLayers::layer(1),
+        render_to_texture_tasks.get(task).unwrap().get_layer(),
     ));
 
     commands.spawn(Camera3dBundle {
@@ -20,7 +20,10 @@ fn setup_scene(
     mut images: ResMut<Assets<Image>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut standard_materials: ResMut<Assets<StandardMaterial>>,
+    mut tasks: ResMut<RenderToTextureTasks>,
 ) {
+    // keep the layer of the scene away from the render to texture tasks
+    tasks.reserve_layer(1);
     let (image_handle, _) = create_render_texture(512, 512, &mut commands, &mut images, 1, true);
 
     commands.spawn((
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use std::cmp::Reverse;
+
+/// Packs rectangles of the given sizes into an area of the given size, row by row from the top left.
+///
+/// Returns the rectangle of each size in the original order, or `None` if they don't fit.
+pub fn pack(sizes: &[UVec2], padding: u32, area: UVec2) -> Option<Vec<URect>> {
+    // placing the tallest rectangles first keeps the rows dense
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|i| Reverse(sizes[*i].y));
+
+    let mut rects = vec![URect::default(); sizes.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for i in order {
+        let size = sizes[i];
+        if x > 0 && x + size.x > area.x {
+            // start a new row
+            x = 0;
+            y += row_height + padding;
+            row_height = 0;
+        }
+        if x + size.x > area.x || y + size.y > area.y {
+            return None;
+        }
+        let min = UVec2::new(x, y);
+        rects[i] = URect::from_corners(min, min + size);
+        x += size.x + padding;
+        row_height = row_height.max(size.y);
+    }
+    Some(rects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_order_of_the_sizes() {
+        let sizes = [UVec2::new(8, 4), UVec2::new(8, 8), UVec2::new(4, 4)];
+        let rects = pack(&sizes, 0, UVec2::new(16, 16)).unwrap();
+        // the tallest rectangle is placed first
+        assert_eq!(rects[1], URect::new(0, 0, 8, 8));
+        for (rect, size) in rects.iter().zip(sizes) {
+            assert_eq!(rect.size(), size);
+        }
+    }
+
+    #[test]
+    fn regions_are_padded_and_disjoint() {
+        let sizes = [UVec2::splat(6); 4];
+        let rects = pack(&sizes, 2, UVec2::splat(14)).unwrap();
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.max.cmple(UVec2::splat(14)).all());
+            for b in &rects[i + 1..] {
+                let padded = IRect::from_corners(a.min.as_ivec2() - 2, a.max.as_ivec2() + 2);
+                let gap = padded.intersect(b.as_irect());
+                assert!(
+                    gap.is_empty(),
+                    "{a:?} and {b:?} are less than 2 pixels apart"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_that_dont_fit() {
+        assert!(pack(&[UVec2::new(17, 1)], 0, UVec2::splat(16)).is_none());
+        assert!(pack(&[UVec2::splat(6); 4], 2, UVec2::splat(13)).is_none());
+        assert_eq!(pack(&[], 0, UVec2::splat(16)), Some(Vec::new()));
+    }
+}
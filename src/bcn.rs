@@ -0,0 +1,653 @@
+//! Pure Rust encoders for the BC1, BC3, BC4, BC5 and BC7 block compression formats.
+//!
+//! The endpoints of each block are fitted along the principal axis of its colors and refined
+//! once with a least squares fit. BC7 only uses mode 6, i.e., one RGBA subset per block.
+
+use crate::component::BcnFormat;
+use bevy::prelude::*;
+
+/// A color with channels in 0..255.
+type Color4 = [f32; 4];
+
+/// The 16 pixels of a 4x4 block, row by row.
+type Block = [Color4; 16];
+
+/// Compresses RGBA8 data of all layers and their mip levels, ordered layer by layer.
+/// Blocks at the border of levels that aren't a multiple of 4 repeat the last row or column.
+pub fn compress(data: &[u8], format: BcnFormat, size: UVec2, levels: u32, layers: u32) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let mut offset = 0;
+    for _ in 0..layers {
+        for level in 0..levels {
+            let level_size = (size >> level).max(UVec2::ONE);
+            let len = (level_size.x * level_size.y) as usize * 4;
+            compress_level(
+                &data[offset..offset + len],
+                format,
+                level_size,
+                &mut compressed,
+            );
+            offset += len;
+        }
+    }
+    compressed
+}
+
+fn compress_level(data: &[u8], format: BcnFormat, size: UVec2, compressed: &mut Vec<u8>) {
+    let blocks = (size + 3) / 4;
+    for y in 0..blocks.y {
+        for x in 0..blocks.x {
+            let block = read_block(data, size, UVec2::new(x, y) * 4);
+            match format {
+                BcnFormat::Bc1 => compressed.extend(bc1_block(&block, false)),
+                BcnFormat::Bc3 => {
+                    compressed.extend(bc4_block(&block.map(|pixel| pixel[3])));
+                    compressed.extend(bc1_block(&block, true));
+                }
+                BcnFormat::Bc4 => compressed.extend(bc4_block(&block.map(|pixel| pixel[0]))),
+                BcnFormat::Bc5 => {
+                    compressed.extend(bc4_block(&block.map(|pixel| pixel[0])));
+                    compressed.extend(bc4_block(&block.map(|pixel| pixel[1])));
+                }
+                BcnFormat::Bc7 => compressed.extend(bc7_block(&block)),
+            }
+        }
+    }
+}
+
+fn read_block(data: &[u8], size: UVec2, min: UVec2) -> Block {
+    let mut block = [[0.0; 4]; 16];
+    for (i, pixel) in block.iter_mut().enumerate() {
+        let position = (min + UVec2::new(i as u32 % 4, i as u32 / 4)).min(size - 1);
+        let start = (position.y * size.x + position.x) as usize * 4;
+        for (channel, value) in pixel.iter_mut().zip(&data[start..start + 4]) {
+            *channel = *value as f32;
+        }
+    }
+    block
+}
+
+fn add(a: Color4, b: Color4) -> Color4 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
+}
+
+fn sub(a: Color4, b: Color4) -> Color4 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]]
+}
+
+fn scale(a: Color4, s: f32) -> Color4 {
+    a.map(|channel| channel * s)
+}
+
+fn dot(a: Color4, b: Color4) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3]
+}
+
+fn lerp(a: Color4, b: Color4, t: f32) -> Color4 {
+    add(scale(a, 1.0 - t), scale(b, t))
+}
+
+fn distance_squared(a: Color4, b: Color4) -> f32 {
+    let d = sub(a, b);
+    dot(d, d)
+}
+
+/// The index of the closest palette entry and its squared distance.
+fn nearest(color: Color4, palette: &[Color4]) -> (usize, f32) {
+    palette
+        .iter()
+        .map(|entry| distance_squared(color, *entry))
+        .enumerate()
+        .fold((0, f32::INFINITY), |best, (index, error)| {
+            if error < best.1 {
+                (index, error)
+            } else {
+                best
+            }
+        })
+}
+
+/// The ends of the segment along the principal axis of the colors that covers all of them.
+fn principal_endpoints(colors: &[Color4]) -> (Color4, Color4) {
+    let n = colors.len().max(1) as f32;
+    let mean = scale(colors.iter().fold([0.0; 4], |sum, c| add(sum, *c)), 1.0 / n);
+
+    let mut covariance = [[0.0; 4]; 4];
+    for color in colors {
+        let d = sub(*color, mean);
+        for (row, di) in covariance.iter_mut().zip(d) {
+            *row = add(*row, scale(d, di));
+        }
+    }
+
+    // Power iteration converges to the eigenvector with the largest eigenvalue. It starts from
+    // the row of the channel that varies most, because a constant vector can be orthogonal to
+    // that eigenvector, e.g., for a checker of red and green.
+    let channel = (0..4)
+        .max_by(|a, b| covariance[*a][*a].total_cmp(&covariance[*b][*b]))
+        .unwrap();
+    let mut axis = covariance[channel];
+    if dot(axis, axis) < 1e-12 {
+        // all colors are the same
+        return (clamp(mean), clamp(mean));
+    }
+    for _ in 0..8 {
+        let next = covariance.map(|row| dot(row, axis));
+        let length = dot(next, next).sqrt();
+        if length < 1e-6 {
+            break;
+        }
+        axis = scale(next, 1.0 / length);
+    }
+    let axis = scale(axis, 1.0 / dot(axis, axis).sqrt());
+
+    let (min, max) = colors
+        .iter()
+        .map(|color| dot(sub(*color, mean), axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), t| {
+            (min.min(t), max.max(t))
+        });
+    if min > max {
+        return (mean, mean);
+    }
+    (
+        clamp(add(mean, scale(axis, min))),
+        clamp(add(mean, scale(axis, max))),
+    )
+}
+
+/// The endpoints that minimize the squared error of the colors interpolated with the weights.
+fn least_squares_endpoints(colors: &[Color4], weights: &[f32]) -> Option<(Color4, Color4)> {
+    let (mut aa, mut bb, mut ab) = (0.0, 0.0, 0.0);
+    let (mut ax, mut bx) = ([0.0; 4], [0.0; 4]);
+    for (color, &w) in colors.iter().zip(weights) {
+        let v = 1.0 - w;
+        aa += v * v;
+        bb += w * w;
+        ab += v * w;
+        ax = add(ax, scale(*color, v));
+        bx = add(bx, scale(*color, w));
+    }
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+    let a = scale(sub(scale(ax, bb), scale(bx, ab)), 1.0 / determinant);
+    let b = scale(sub(scale(bx, aa), scale(ax, ab)), 1.0 / determinant);
+    Some((clamp(a), clamp(b)))
+}
+
+fn clamp(color: Color4) -> Color4 {
+    color.map(|channel| channel.clamp(0.0, 255.0))
+}
+
+/// An encoded block together with the weights of its pixels and its squared error.
+struct Fit<T> {
+    block: T,
+    weights: [f32; 16],
+    error: f32,
+}
+
+/// Encodes the block with the endpoints of the principal axis, then with the endpoints
+/// of a least squares fit to the resulting weights, and keeps the better one.
+fn fit<T>(colors: &[Color4], encode: impl Fn(Color4, Color4) -> Fit<T>) -> T {
+    let (a, b) = principal_endpoints(colors);
+    let first = encode(a, b);
+    let Some((a, b)) = least_squares_endpoints(colors, &first.weights) else {
+        return first.block;
+    };
+    let second = encode(a, b);
+    if second.error < first.error {
+        second.block
+    } else {
+        first.block
+    }
+}
+
+fn to_rgb565(color: Color4) -> u16 {
+    let r = (color[0] * 31.0 / 255.0).round() as u16;
+    let g = (color[1] * 63.0 / 255.0).round() as u16;
+    let b = (color[2] * 31.0 / 255.0).round() as u16;
+    (r << 11) | (g << 5) | b
+}
+
+fn from_rgb565(color: u16) -> Color4 {
+    let (r, g, b) = (color >> 11, (color >> 5) & 0x3f, color & 0x1f);
+    [
+        ((r << 3) | (r >> 2)) as f32,
+        ((g << 2) | (g >> 4)) as f32,
+        ((b << 3) | (b >> 2)) as f32,
+        0.0,
+    ]
+}
+
+/// Encodes the colors of a block. The color blocks of BC3 always interpolate four colors,
+/// while BC1 uses three colors and transparent black if any pixel is transparent.
+fn bc1_block(block: &Block, four_colors_only: bool) -> [u8; 8] {
+    let transparent = block.map(|pixel| !four_colors_only && pixel[3] < 128.0);
+    let colors = block.map(|pixel| [pixel[0], pixel[1], pixel[2], 0.0]);
+    let opaque: Vec<Color4> = colors
+        .iter()
+        .zip(transparent)
+        .filter(|(_, transparent)| !transparent)
+        .map(|(color, _)| *color)
+        .collect();
+    if opaque.is_empty() {
+        // equal endpoints select three colors, index 3 is transparent black
+        return [0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+    }
+    let three_colors = transparent.contains(&true);
+
+    fit(&opaque, |a, b| {
+        let (mut c0, mut c1) = (to_rgb565(a), to_rgb565(b));
+        // the order of the endpoints selects the mode
+        if three_colors == (c0 > c1) {
+            std::mem::swap(&mut c0, &mut c1);
+        }
+        let (e0, e1) = (from_rgb565(c0), from_rgb565(c1));
+        let (palette, weights): (Vec<Color4>, &[f32]) = if four_colors_only || c0 > c1 {
+            (
+                vec![e0, e1, lerp(e0, e1, 1.0 / 3.0), lerp(e0, e1, 2.0 / 3.0)],
+                &[0.0, 1.0, 1.0 / 3.0, 2.0 / 3.0],
+            )
+        } else {
+            (vec![e0, e1, lerp(e0, e1, 0.5)], &[0.0, 1.0, 0.5])
+        };
+
+        let mut indices = 0u32;
+        let mut opaque_weights = [0.0; 16];
+        let mut error = 0.0;
+        let mut opaque_count = 0;
+        for (i, (color, transparent)) in colors.iter().zip(transparent).enumerate() {
+            let index = if transparent {
+                3
+            } else {
+                let (index, distance) = nearest(*color, &palette);
+                opaque_weights[opaque_count] = weights[index];
+                opaque_count += 1;
+                error += distance;
+                index
+            };
+            indices |= (index as u32) << (2 * i);
+        }
+
+        let mut block = [0; 8];
+        block[0..2].copy_from_slice(&c0.to_le_bytes());
+        block[2..4].copy_from_slice(&c1.to_le_bytes());
+        block[4..8].copy_from_slice(&indices.to_le_bytes());
+        Fit {
+            block,
+            weights: opaque_weights,
+            error,
+        }
+    })
+}
+
+/// Encodes a single channel between its minimum and maximum with eight levels.
+fn bc4_block(values: &[f32; 16]) -> [u8; 8] {
+    let max = values.iter().fold(0.0f32, |max, v| max.max(*v)).round() as u8;
+    let min = values.iter().fold(255.0f32, |min, v| min.min(*v)).round() as u8;
+    // equal endpoints select six interpolated levels, index 0 is still the first endpoint
+    let (a, b) = (f32::from(max), f32::from(min));
+    let mut levels = vec![a, b];
+    levels.extend((1..7).map(|i| (a * (7 - i) as f32 + b * i as f32) / 7.0));
+    let palette: Vec<Color4> = levels
+        .into_iter()
+        .map(|level| [level, 0.0, 0.0, 0.0])
+        .collect();
+
+    let mut indices = 0u64;
+    for (i, value) in values.iter().enumerate() {
+        let (index, _) = nearest([*value, 0.0, 0.0, 0.0], &palette);
+        indices |= (index as u64) << (3 * i);
+    }
+
+    let mut block = [0; 8];
+    block[0] = max;
+    block[1] = min;
+    block[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    block
+}
+
+/// The interpolation weights of 4 bit indices, in 64ths.
+const BC7_WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Quantizes an endpoint to 7 bits per channel and the shared p-bit with the smaller error.
+fn bc7_endpoint(color: Color4) -> ([u8; 4], u8) {
+    (0..=1u8)
+        .map(|p| {
+            let quantized =
+                color.map(|channel| ((channel - p as f32) / 2.0).round().clamp(0.0, 127.0) as u8);
+            let error = distance_squared(color, bc7_decode_endpoint(quantized, p));
+            (quantized, p, error)
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(quantized, p, _)| (quantized, p))
+        .unwrap()
+}
+
+fn bc7_decode_endpoint(quantized: [u8; 4], p: u8) -> Color4 {
+    quantized.map(|channel| ((channel << 1) | p) as f32)
+}
+
+/// Encodes a block in mode 6, i.e., with 7 bit RGBA endpoints, a p-bit per endpoint
+/// and 4 bit indices.
+fn bc7_block(block: &Block) -> [u8; 16] {
+    fit(block, |a, b| {
+        let (mut q0, mut p0) = bc7_endpoint(a);
+        let (mut q1, mut p1) = bc7_endpoint(b);
+        let (e0, e1) = (bc7_decode_endpoint(q0, p0), bc7_decode_endpoint(q1, p1));
+        let palette: Vec<Color4> = BC7_WEIGHTS
+            .iter()
+            .map(|w| {
+                let w = *w as f32;
+                add(scale(e0, 64.0 - w), scale(e1, w)).map(|c| ((c + 32.0) / 64.0).floor())
+            })
+            .collect();
+
+        let mut indices = [0u8; 16];
+        let mut weights = [0.0; 16];
+        let mut error = 0.0;
+        for (i, pixel) in block.iter().enumerate() {
+            let (index, distance) = nearest(*pixel, &palette);
+            indices[i] = index as u8;
+            weights[i] = BC7_WEIGHTS[index] as f32 / 64.0;
+            error += distance;
+        }
+
+        // the most significant bit of the first index is implied to be 0
+        if indices[0] >= 8 {
+            std::mem::swap(&mut q0, &mut q1);
+            std::mem::swap(&mut p0, &mut p1);
+            indices = indices.map(|index| 15 - index);
+        }
+
+        let mut bits = 1u128 << 6;
+        let mut position = 7;
+        let mut write = |value: u8, count: u32| {
+            bits |= (value as u128) << position;
+            position += count;
+        };
+        for channel in 0..4 {
+            write(q0[channel], 7);
+            write(q1[channel], 7);
+        }
+        write(p0, 1);
+        write(p1, 1);
+        write(indices[0], 3);
+        for index in &indices[1..] {
+            write(*index, 4);
+        }
+
+        Fit {
+            block: bits.to_le_bytes(),
+            weights,
+            error,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_bc1(block: &[u8], four_colors_only: bool) -> [Color4; 16] {
+        let c0 = u16::from_le_bytes([block[0], block[1]]);
+        let c1 = u16::from_le_bytes([block[2], block[3]]);
+        let opaque = |color: u16| {
+            let mut color = from_rgb565(color);
+            color[3] = 255.0;
+            color
+        };
+        let (e0, e1) = (opaque(c0), opaque(c1));
+        let palette = if four_colors_only || c0 > c1 {
+            [
+                e0,
+                e1,
+                scale(add(scale(e0, 2.0), e1), 1.0 / 3.0),
+                scale(add(e0, scale(e1, 2.0)), 1.0 / 3.0),
+            ]
+        } else {
+            [e0, e1, scale(add(e0, e1), 0.5), [0.0; 4]]
+        };
+        let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+        std::array::from_fn(|i| palette[(indices >> (2 * i) & 3) as usize])
+    }
+
+    fn decode_bc4(block: &[u8]) -> [f32; 16] {
+        let (a, b) = (f32::from(block[0]), f32::from(block[1]));
+        let mut levels = vec![a, b];
+        if block[0] > block[1] {
+            levels.extend((1..7).map(|i| (a * (7 - i) as f32 + b * i as f32) / 7.0));
+        } else {
+            levels.extend((1..5).map(|i| (a * (5 - i) as f32 + b * i as f32) / 5.0));
+            levels.extend([0.0, 255.0]);
+        }
+        let mut bytes = [0; 8];
+        bytes[..6].copy_from_slice(&block[2..8]);
+        let indices = u64::from_le_bytes(bytes);
+        std::array::from_fn(|i| levels[(indices >> (3 * i) & 7) as usize])
+    }
+
+    fn decode_bc7(block: &[u8]) -> [Color4; 16] {
+        let bits = u128::from_le_bytes(block.try_into().unwrap());
+        assert_eq!(bits & 0x7f, 1 << 6, "only mode 6 is decoded");
+        let mut position = 7;
+        let mut read = |count: u32| {
+            let value = (bits >> position) as u32 & ((1 << count) - 1);
+            position += count;
+            value
+        };
+        let (mut q0, mut q1) = ([0; 4], [0; 4]);
+        for (c0, c1) in q0.iter_mut().zip(&mut q1) {
+            *c0 = read(7);
+            *c1 = read(7);
+        }
+        let (p0, p1) = (read(1), read(1));
+        let e0 = q0.map(|c| (c << 1) | p0);
+        let e1 = q1.map(|c| (c << 1) | p1);
+        std::array::from_fn(|i| {
+            let w = BC7_WEIGHTS[read(if i == 0 { 3 } else { 4 }) as usize];
+            std::array::from_fn(|c| (((64 - w) * e0[c] + w * e1[c] + 32) >> 6) as f32)
+        })
+    }
+
+    /// Decodes the blocks of a single level back to RGBA pixels.
+    /// Channels a format doesn't store are 0, and 255 for alpha.
+    fn decode(compressed: &[u8], format: BcnFormat, size: UVec2) -> Vec<Color4> {
+        let block_size = match format {
+            BcnFormat::Bc1 | BcnFormat::Bc4 => 8,
+            BcnFormat::Bc3 | BcnFormat::Bc5 | BcnFormat::Bc7 => 16,
+        };
+        let blocks = (size + 3) / 4;
+        assert_eq!(
+            compressed.len(),
+            (blocks.x * blocks.y) as usize * block_size
+        );
+
+        let mut pixels = vec![[0.0; 4]; (size.x * size.y) as usize];
+        for (index, block) in compressed.chunks_exact(block_size).enumerate() {
+            let decoded: [Color4; 16] = match format {
+                BcnFormat::Bc1 => decode_bc1(block, false),
+                BcnFormat::Bc3 => {
+                    let alpha = decode_bc4(&block[..8]);
+                    let mut colors = decode_bc1(&block[8..], true);
+                    for (color, alpha) in colors.iter_mut().zip(alpha) {
+                        color[3] = alpha;
+                    }
+                    colors
+                }
+                BcnFormat::Bc4 => decode_bc4(block).map(|r| [r, 0.0, 0.0, 255.0]),
+                BcnFormat::Bc5 => {
+                    let (r, g) = (decode_bc4(&block[..8]), decode_bc4(&block[8..]));
+                    std::array::from_fn(|i| [r[i], g[i], 0.0, 255.0])
+                }
+                BcnFormat::Bc7 => decode_bc7(block),
+            };
+            let min = UVec2::new(index as u32 % blocks.x, index as u32 / blocks.x) * 4;
+            for (i, color) in decoded.into_iter().enumerate() {
+                let position = min + UVec2::new(i as u32 % 4, i as u32 / 4);
+                if position.x < size.x && position.y < size.y {
+                    pixels[(position.y * size.x + position.x) as usize] = color;
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Compresses the RGBA8 pixels and returns the largest and the mean error of any channel
+    /// the format stores.
+    fn round_trip(pixels: &[[u8; 4]], format: BcnFormat, size: UVec2) -> (f32, f32) {
+        let data: Vec<u8> = pixels.iter().flatten().copied().collect();
+        let decoded = decode(&compress(&data, format, size, 1, 1), format, size);
+        let channels = match format {
+            BcnFormat::Bc4 => 0..1,
+            BcnFormat::Bc5 => 0..2,
+            // the alpha of BC1 is a single bit
+            BcnFormat::Bc1 => 0..3,
+            BcnFormat::Bc3 | BcnFormat::Bc7 => 0..4,
+        };
+        let errors: Vec<f32> = pixels
+            .iter()
+            .zip(&decoded)
+            .flat_map(|(pixel, decoded)| {
+                channels
+                    .clone()
+                    .map(move |c| (f32::from(pixel[c]) - decoded[c]).abs())
+            })
+            .collect();
+        let max = errors.iter().fold(0.0f32, |max, e| max.max(*e));
+        (max, errors.iter().sum::<f32>() / errors.len() as f32)
+    }
+
+    const FORMATS: [BcnFormat; 5] = [
+        BcnFormat::Bc1,
+        BcnFormat::Bc3,
+        BcnFormat::Bc4,
+        BcnFormat::Bc5,
+        BcnFormat::Bc7,
+    ];
+
+    /// A diagonal gradient between two colors, so the colors of each block lie on a line.
+    fn gradient(size: UVec2) -> Vec<[u8; 4]> {
+        let (from, to) = ([20.0, 240.0, 100.0, 255.0], [220.0, 60.0, 150.0, 155.0]);
+        let steps = (size.x + size.y - 2) as f32;
+        (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| (x + y) as f32 / steps))
+            .map(|t| lerp(from, to, t).map(|c| c as u8))
+            .collect()
+    }
+
+    #[test]
+    fn constant_blocks() {
+        let size = UVec2::new(8, 4);
+        for color in [
+            [0, 0, 0, 255],
+            [255; 4],
+            [13, 200, 77, 255],
+            [90, 40, 250, 160],
+        ] {
+            let pixels = vec![color; 32];
+            for format in FORMATS {
+                let (max, _) = round_trip(&pixels, format, size);
+                // BC1 and the colors of BC3 are quantized to 5 and 6 bits
+                let bound = match format {
+                    BcnFormat::Bc1 | BcnFormat::Bc3 => 4.0,
+                    BcnFormat::Bc4 | BcnFormat::Bc5 | BcnFormat::Bc7 => 1.0,
+                };
+                assert!(max <= bound, "{format:?} {color:?}: {max}");
+            }
+        }
+    }
+
+    #[test]
+    fn opposing_channels() {
+        // a checker of red and green has no variance along the diagonal of the color cube
+        let pixels: Vec<[u8; 4]> = (0..16)
+            .map(|i| {
+                if (i % 4 + i / 4) % 2 == 0 {
+                    [255, 0, 0, 255]
+                } else {
+                    [0, 255, 0, 255]
+                }
+            })
+            .collect();
+        for format in [BcnFormat::Bc1, BcnFormat::Bc3, BcnFormat::Bc7] {
+            let (max, _) = round_trip(&pixels, format, UVec2::splat(4));
+            assert!(max <= 1.0, "{format:?}: {max}");
+        }
+        // the same for the two channels of BC5
+        let (max, _) = round_trip(&pixels, BcnFormat::Bc5, UVec2::splat(4));
+        assert!(max <= 1.0, "Bc5: {max}");
+    }
+
+    #[test]
+    fn gradients() {
+        // the colors of the smaller image change faster, and its blocks reach past it
+        for (size, scale) in [(UVec2::new(16, 8), 1.0), (UVec2::new(6, 3), 2.0)] {
+            let pixels = gradient(size);
+            for (format, max_bound, mean_bound) in [
+                (BcnFormat::Bc1, 12.0, 4.0),
+                (BcnFormat::Bc3, 12.0, 4.0),
+                (BcnFormat::Bc4, 4.0, 2.5),
+                (BcnFormat::Bc5, 4.0, 2.5),
+                (BcnFormat::Bc7, 2.0, 1.0),
+            ] {
+                let (max, mean) = round_trip(&pixels, format, size);
+                assert!(
+                    max <= max_bound * scale,
+                    "{format:?} {size}: largest error {max}"
+                );
+                assert!(
+                    mean <= mean_bound * scale,
+                    "{format:?} {size}: mean error {mean}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bc1_transparent_pixels() {
+        let pixels: Vec<[u8; 4]> = (0..16)
+            .map(|i| match i % 3 {
+                0 => [0, 0, 0, 0],
+                1 => [200, 100, 50, 255],
+                _ => [50, 100, 200, 255],
+            })
+            .collect();
+        let data: Vec<u8> = pixels.iter().flatten().copied().collect();
+        let size = UVec2::splat(4);
+        let decoded = decode(
+            &compress(&data, BcnFormat::Bc1, size, 1, 1),
+            BcnFormat::Bc1,
+            size,
+        );
+        for (pixel, decoded) in pixels.iter().zip(&decoded) {
+            if pixel[3] == 0 {
+                assert_eq!(*decoded, [0.0; 4]);
+            } else {
+                assert_eq!(decoded[3], 255.0);
+                for c in 0..3 {
+                    assert!((f32::from(pixel[c]) - decoded[c]).abs() <= 8.0);
+                }
+            }
+        }
+
+        let transparent = vec![0; 64];
+        let block = compress(&transparent, BcnFormat::Bc1, size, 1, 1);
+        assert_eq!(decode(&block, BcnFormat::Bc1, size), vec![[0.0; 4]; 16]);
+    }
+
+    #[test]
+    fn levels_and_layers() {
+        // 8x5, 4x2, 2x1 and 1x1 pixels need 4, 1, 1 and 1 blocks
+        let size = UVec2::new(8, 5);
+        let texels = 40 + 8 + 2 + 1;
+        let data = vec![128; texels * 4 * 2];
+        assert_eq!(compress(&data, BcnFormat::Bc1, size, 4, 2).len(), 7 * 8 * 2);
+        assert_eq!(
+            compress(&data, BcnFormat::Bc7, size, 4, 2).len(),
+            7 * 16 * 2
+        );
+    }
+}
@@ -0,0 +1,692 @@
+#[cfg(feature = "atlas")]
+use crate::render::RenderToTextureTaskId;
+use crate::{
+    events::RenderToTextureFinished,
+    render::{RenderToTextureTasks, TaskResource},
+    resample::{self, RenderToTextureFilter},
+};
+use bevy::{
+    core_pipeline::prepass::{MOTION_VECTOR_PREPASS_FORMAT, NORMAL_PREPASS_FORMAT},
+    prelude::*,
+    render::{
+        render_resource::TextureFormat,
+        texture::{ImageSampler, ImageSamplerDescriptor},
+    },
+    utils::HashMap,
+};
+use std::f32::consts::FRAC_PI_2;
+
+/// How often a render to texture task renders.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum RenderToTextureMode {
+    /// Render once and despawn the camera as soon as the result is available.
+    #[default]
+    Once,
+    /// Keep the camera around so the task can be rendered again using `rerender`.
+    OnDemand,
+}
+
+/// How the rendered colors are stored.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum RenderToTextureColorSpace {
+    /// Colors are stored in sRGB space. Use this for textures that are displayed as colors.
+    #[default]
+    Srgb,
+    /// Values are stored as they are written by the shaders. Use this for data textures like masks,
+    /// normal maps or flow maps.
+    Linear,
+}
+
+/// The kind of camera that renders a task.
+#[derive(Default, Clone, Copy, PartialEq, Debug, Reflect)]
+pub enum RenderToTextureCamera {
+    /// A 2d camera for sprites and 2d meshes, centered at the origin with one unit per pixel.
+    #[default]
+    Camera2d,
+    /// A 3d camera with a perspective projection. The aspect ratio is taken from the texture size.
+    Perspective {
+        /// Vertical field of view in radians.
+        fov: f32,
+        near: f32,
+        far: f32,
+    },
+    /// A 3d camera with an orthographic projection showing exactly `area` in view space.
+    Orthographic { area: Rect, near: f32, far: f32 },
+    /// Use an existing camera entity, e.g., to get custom tonemapping, bloom or post-processing.
+    /// The camera is retargeted to the task's image and its `is_active` flag is managed by the task.
+    /// It also gets the task's `RenderLayers` unless it already has some.
+    /// The camera is not despawned when the task is freed.
+    Existing(Entity),
+}
+
+impl RenderToTextureCamera {
+    /// A perspective camera using bevy's default projection.
+    pub fn perspective() -> Self {
+        let projection = PerspectiveProjection::default();
+        Self::Perspective {
+            fov: projection.fov,
+            near: projection.near,
+            far: projection.far,
+        }
+    }
+
+    /// An orthographic camera showing the given area in view space.
+    pub fn orthographic(area: Rect) -> Self {
+        let projection = OrthographicProjection::default();
+        Self::Orthographic {
+            area,
+            near: projection.near,
+            far: projection.far,
+        }
+    }
+}
+
+/// The sampler of the images a task produces.
+#[derive(Default, Clone, Debug)]
+pub enum RenderToTextureSampler {
+    /// Trilinear filtering for mipmapped and compressed results, the app's default sampler otherwise.
+    #[default]
+    Auto,
+    /// Use the descriptor for every image, e.g., nearest filtering for pixel art
+    /// or repeat addressing for tiling textures.
+    Descriptor(ImageSamplerDescriptor),
+}
+
+impl PartialEq for RenderToTextureSampler {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Auto, Self::Auto) => true,
+            // the descriptor itself can't be compared
+            (Self::Descriptor(a), Self::Descriptor(b)) => a.as_wgpu() == b.as_wgpu(),
+            _ => false,
+        }
+    }
+}
+
+impl From<ImageSamplerDescriptor> for RenderToTextureSampler {
+    fn from(descriptor: ImageSamplerDescriptor) -> Self {
+        Self::Descriptor(descriptor)
+    }
+}
+
+impl RenderToTextureSampler {
+    /// The sampler of an image with or without mipmaps.
+    pub fn image_sampler(&self, mipmapped: bool) -> ImageSampler {
+        match self {
+            Self::Auto if mipmapped => ImageSampler::linear(),
+            Self::Auto => ImageSampler::Default,
+            Self::Descriptor(descriptor) => ImageSampler::Descriptor(descriptor.clone()),
+        }
+    }
+}
+
+/// The layers of the texture a task renders.
+#[derive(Default, Clone, PartialEq, Debug, Reflect)]
+pub enum RenderToTextureLayout {
+    /// A single 2d texture.
+    #[default]
+    Single,
+    /// A cubemap rendered by six perspective cameras with a field of view of 90° placed at the
+    /// task's transform. The faces are ordered +X, -X, +Y, -Y, +Z, -Z. Near and far are taken
+    /// from a perspective `camera`. Requires a square size.
+    Cube,
+    /// A texture array with one layer per transform, each rendered by a camera of the task's kind.
+    Array(Vec<Transform>),
+    /// A texture atlas whose regions are rendered by other tasks, see
+    /// [`RenderToTextureLayout::AtlasRegion`]. The regions are packed automatically into `size`
+    /// whenever one is added, and the atlas has no camera of its own.
+    ///
+    /// The result comes with a [`RenderToTextureAtlas`] mapping the tasks of the regions to
+    /// their textures in a `TextureAtlasLayout`. The atlas waits for its first region before it
+    /// renders. Requires the `atlas` feature.
+    #[cfg(feature = "atlas")]
+    Atlas {
+        /// Pixels left empty between regions, to avoid bleeding when the atlas is filtered.
+        padding: u32,
+    },
+    /// A region of `size` pixels in the atlas task with the given id, rendered by this task's
+    /// camera. Background, format and supersampling are taken from the atlas.
+    ///
+    /// Region tasks don't produce results of their own: a region is rendered whenever its atlas
+    /// renders, and the region tasks are removed together with it. Requires the `atlas` feature.
+    ///
+    /// All regions render the render layer of their atlas, which is what their `get_layer`
+    /// returns, so an atlas can have as many regions as fit into its size. Place the contents of
+    /// each region apart from the others and point the region's camera at them.
+    #[cfg(feature = "atlas")]
+    AtlasRegion(RenderToTextureTaskId),
+}
+
+impl RenderToTextureLayout {
+    /// Number of layers of the texture.
+    pub fn layer_count(&self) -> u32 {
+        match self {
+            Self::Single => 1,
+            #[cfg(feature = "atlas")]
+            Self::Atlas { .. } | Self::AtlasRegion(_) => 1,
+            Self::Cube => 6,
+            Self::Array(transforms) => transforms.len() as u32,
+        }
+    }
+}
+
+/// Forward and up direction of the camera of each cube face, following the cubemap convention
+/// of wgpu.
+const CUBE_FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Y),
+    (Vec3::Y, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::Z),
+    (Vec3::Z, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y),
+];
+
+/// What the task's camera renders on top of.
+///
+/// Patterns are written to the render target before rendering and the camera's output is
+/// blended over them. Existing cameras keep their own clear color and output mode.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub enum RenderToTextureBackground {
+    /// Clear the target with the color.
+    Color(Color),
+    /// Don't clear the target, so the results of several renders accumulate.
+    Keep,
+    /// A checkerboard of squares with a side length of `size` pixels, starting with `a` in the top left.
+    Checkerboard { size: u32, a: Color, b: Color },
+    /// A vertical gradient, interpolated in linear space.
+    Gradient { top: Color, bottom: Color },
+}
+
+impl Default for RenderToTextureBackground {
+    fn default() -> Self {
+        Self::Color(Color::NONE)
+    }
+}
+
+impl RenderToTextureBackground {
+    /// The color of the background at the given pixel of a texture of the given size.
+    pub fn color_at(&self, x: u32, y: u32, size: UVec2) -> Option<Color> {
+        match *self {
+            Self::Color(_) | Self::Keep => None,
+            Self::Checkerboard { size: square, a, b } => {
+                let square = square.max(1);
+                Some(if (x / square + y / square) % 2 == 0 {
+                    a
+                } else {
+                    b
+                })
+            }
+            Self::Gradient { top, bottom } => {
+                let t = y as f32 / (size.y.max(2) - 1) as f32;
+                let top = Vec4::from_array(top.as_linear_rgba_f32());
+                let bottom = Vec4::from_array(bottom.as_linear_rgba_f32());
+                Some(Color::rgba_linear_from_array(top.lerp(bottom, t)))
+            }
+        }
+    }
+
+    /// Whether the background is a pattern that is written to the target before each render.
+    pub fn is_pattern(&self) -> bool {
+        matches!(self, Self::Checkerboard { .. } | Self::Gradient { .. })
+    }
+}
+
+/// The images a task can produce.
+///
+/// Material properties like albedo or roughness are not available, since Bevy only writes them
+/// to the packed G-buffer of the deferred prepass.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect)]
+pub enum RenderToTextureOutput {
+    /// The rendered image.
+    Color,
+    /// The depth buffer of the task's camera as an `R32Float` image.
+    Depth,
+    /// The world space normals of the normal prepass as an `Rgb10a2Unorm` image,
+    /// mapped from -1..1 to 0..1.
+    Normal,
+    /// The screen space motion vectors of the motion vector prepass as an `Rg16Float` image.
+    MotionVectors,
+}
+
+impl RenderToTextureOutput {
+    /// The format of the output's image. `None` for the color image, which uses the task's format.
+    pub fn format(&self) -> Option<TextureFormat> {
+        match self {
+            Self::Color => None,
+            Self::Depth => Some(TextureFormat::R32Float),
+            Self::Normal => Some(NORMAL_PREPASS_FORMAT),
+            Self::MotionVectors => Some(MOTION_VECTOR_PREPASS_FORMAT),
+        }
+    }
+}
+
+/// How the depth buffer is read back.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum RenderToTextureDepth {
+    /// The values of the depth buffer, i.e., bevy's reversed z with 1 at the near plane and 0 at the far plane.
+    Raw,
+    /// The distance from the camera plane in world units. Points at infinity are `f32::INFINITY`.
+    Linear,
+}
+
+/// The codec basis universal compresses to.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub enum BasisCompressionFormat {
+    /// Small files with a lower quality. `quality` ranges from 1 to 255.
+    Etc1s { quality: u32 },
+    /// Higher quality at about 8 bits per pixel. `quality` ranges from 0 to 4.
+    ///
+    /// `rdo` enables rate distortion optimization with the given quality scalar, which makes
+    /// the file more compressible by lossless compressors like zstd. Higher values give smaller
+    /// files of lower quality, 1.0 is a good start.
+    Uastc { quality: u32, rdo: Option<f32> },
+}
+
+impl Default for BasisCompressionFormat {
+    fn default() -> Self {
+        Self::Uastc {
+            quality: 2,
+            rdo: None,
+        }
+    }
+}
+
+/// The block compression formats the `compress-bcn` feature encodes to.
+/// The formats with color channels use the sRGB variant if the task's format is sRGB.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum BcnFormat {
+    /// RGB at 4 bits per pixel. Pixels with an alpha below 0.5 become transparent black.
+    Bc1,
+    /// RGBA at 8 bits per pixel with a separately compressed alpha channel.
+    Bc3,
+    /// The red channel at 4 bits per pixel, e.g., for height or roughness maps.
+    Bc4,
+    /// The red and green channels at 8 bits per pixel, e.g., for normal maps.
+    Bc5,
+    /// RGBA at 8 bits per pixel with a higher quality than BC1 and BC3.
+    Bc7,
+}
+
+impl BcnFormat {
+    /// The format of the compressed image.
+    pub fn texture_format(&self, srgb: bool) -> TextureFormat {
+        match (self, srgb) {
+            (Self::Bc1, false) => TextureFormat::Bc1RgbaUnorm,
+            (Self::Bc1, true) => TextureFormat::Bc1RgbaUnormSrgb,
+            (Self::Bc3, false) => TextureFormat::Bc3RgbaUnorm,
+            (Self::Bc3, true) => TextureFormat::Bc3RgbaUnormSrgb,
+            (Self::Bc4, _) => TextureFormat::Bc4RUnorm,
+            (Self::Bc5, _) => TextureFormat::Bc5RgUnorm,
+            (Self::Bc7, false) => TextureFormat::Bc7RgbaUnorm,
+            (Self::Bc7, true) => TextureFormat::Bc7RgbaUnormSrgb,
+        }
+    }
+}
+
+/// The backend that compresses the results of tasks with `compress` set.
+#[derive(Default, Clone, Copy, PartialEq, Debug, Reflect)]
+pub enum RenderToTextureCompressor {
+    /// Basis universal, transcoded to a format the device supports. Requires the `compress` feature.
+    #[default]
+    Basis,
+    /// A block compressed format that devices supporting `CompressedImageFormats::BC` upload directly.
+    /// Requires the `compress-bcn` feature and a size that is a multiple of 4.
+    Bcn(BcnFormat),
+}
+
+/// The container of the data that `RenderToTextureTasks::read` returns.
+#[derive(Default, Clone, Copy, PartialEq, Debug, Reflect)]
+pub enum RenderToTextureContainer {
+    /// The texel data of the image, layer by layer with the mip levels of each layer,
+    /// the blocks of block compressed results or the basis file of basis compressed results.
+    #[default]
+    Raw,
+    /// A KTX2 file that can be written to disk and loaded by Bevy's `ktx2` loader.
+    /// Holds uncompressed, block compressed or UASTC data, but not ETC1S. Requires the `ktx2` feature.
+    ///
+    /// `zstd_level` supercompresses each mip level with zstd and requires the `zstd` feature.
+    Ktx2 { zstd_level: Option<i32> },
+}
+
+/// How results are compressed with basis universal.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct BasisCompressionSettings {
+    pub format: BasisCompressionFormat,
+    /// Generate mipmaps while compressing. The encoder filters the levels with a Kaiser window,
+    /// not with a [`RenderToTextureFilter`], because the basis-universal bindings don't expose
+    /// its filter setting.
+    pub mipmaps: bool,
+    /// The smallest width or height of a generated mip level.
+    pub mipmap_smallest_dimension: u32,
+    /// Tune the compression for normal maps, which should also use a linear color space.
+    pub normal_map: bool,
+    /// Number of threads the compressor uses.
+    pub threads: u32,
+}
+
+impl Default for BasisCompressionSettings {
+    fn default() -> Self {
+        Self {
+            format: BasisCompressionFormat::default(),
+            mipmaps: true,
+            mipmap_smallest_dimension: 1,
+            normal_map: false,
+            threads: 4,
+        }
+    }
+}
+
+impl BasisCompressionSettings {
+    /// Why the settings can't be passed to the compressor, if they can't.
+    pub fn validate(&self) -> Result<(), String> {
+        match self.format {
+            BasisCompressionFormat::Etc1s { quality } if !(1..=255).contains(&quality) => {
+                return Err(format!("ETC1S quality {quality} is not in 1..=255"));
+            }
+            BasisCompressionFormat::Uastc { quality, .. } if quality > 4 => {
+                return Err(format!("UASTC quality {quality} is not in 0..=4"));
+            }
+            BasisCompressionFormat::Uastc { rdo: Some(rdo), .. } if rdo.is_nan() || rdo <= 0.0 => {
+                return Err("the UASTC rdo quality scalar has to be positive".to_string());
+            }
+            _ => {}
+        }
+        if self.threads == 0 {
+            return Err("basis compression needs at least one thread".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Describes a texture to render.
+///
+/// Either pass it to `RenderToTextureTasks::add` or spawn it as a component. In the latter case,
+/// the plugin creates a task whose camera and export bundle become children of the entity,
+/// inserts a [`TaskResource`] with the task's id and a [`RenderToTextureResult`] when the
+/// image is ready. Changing the component renders the texture again, despawning it frees the task.
+///
+/// Multisampling follows the app's `Msaa` resource, since Bevy has no sample count per camera.
+/// Multisampled cameras resolve their samples before writing to the target, so the readback is unaffected.
+/// To antialias a single task, use `supersampling` instead.
+#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct RenderToTexture {
+    /// Size of the texture in pixels.
+    pub size: UVec2,
+    /// Format of the render target and the resulting image.
+    #[reflect(ignore, default = "default_format")]
+    pub format: TextureFormat,
+    /// Selects the sRGB or linear variant of `format`. `None` uses `format` as it is.
+    pub color_space: Option<RenderToTextureColorSpace>,
+    /// Whether to compress the result with the `compressor`.
+    pub compress: bool,
+    pub compressor: RenderToTextureCompressor,
+    /// How to compress with basis universal if `compress` is set.
+    pub basis: BasisCompressionSettings,
+    pub container: RenderToTextureContainer,
+    pub mode: RenderToTextureMode,
+    pub camera: RenderToTextureCamera,
+    /// Transform of the task's camera. Uses the default transform of the camera bundle if `None`.
+    /// When spawned as a component, the transform is relative to the entity.
+    pub transform: Option<Transform>,
+    pub background: RenderToTextureBackground,
+    /// Render at this multiple of `size` and scale the result down on the CPU, which also
+    /// antialiases aliasing inside of shaders. 1 disables supersampling.
+    ///
+    /// 2d cameras are zoomed so they still show the same area.
+    pub supersampling: u32,
+    /// Filter used to scale supersampled results down.
+    pub downsample_filter: RenderToTextureFilter,
+    /// Also read back the depth buffer as [`RenderToTextureOutput::Depth`].
+    /// Requires a 3d camera and `Msaa::Off`, see [`MultisampledOutput`](crate::RenderToTextureError::MultisampledOutput).
+    pub depth: Option<RenderToTextureDepth>,
+    /// Also read back the normals as [`RenderToTextureOutput::Normal`] using a normal prepass.
+    /// Requires a 3d camera and `Msaa::Off`, see [`MultisampledOutput`](crate::RenderToTextureError::MultisampledOutput).
+    pub normals: bool,
+    /// Also read back the motion vectors as [`RenderToTextureOutput::MotionVectors`] using a motion vector prepass.
+    /// Requires a 3d camera and `Msaa::Off`, see [`MultisampledOutput`](crate::RenderToTextureError::MultisampledOutput).
+    pub motion_vectors: bool,
+    /// Generate a full mip chain on the CPU and sample the result trilinearly.
+    /// Basis compressed results have mipmaps if `basis.mipmaps` is set instead.
+    pub mipmaps: bool,
+    /// The sampler of the resulting images, including extra outputs like the depth buffer.
+    #[reflect(ignore)]
+    pub sampler: RenderToTextureSampler,
+    /// Render a cubemap or a texture array instead of a single texture.
+    /// Layered textures can't be compressed, have no extra outputs and don't support existing cameras.
+    pub layout: RenderToTextureLayout,
+}
+
+fn default_format() -> TextureFormat {
+    TextureFormat::Rgba8UnormSrgb
+}
+
+impl Default for RenderToTexture {
+    fn default() -> Self {
+        Self {
+            size: UVec2::new(512, 512),
+            format: default_format(),
+            color_space: None,
+            compress: false,
+            compressor: RenderToTextureCompressor::Basis,
+            basis: BasisCompressionSettings::default(),
+            container: RenderToTextureContainer::Raw,
+            mode: RenderToTextureMode::Once,
+            camera: RenderToTextureCamera::Camera2d,
+            transform: None,
+            background: RenderToTextureBackground::default(),
+            supersampling: 1,
+            downsample_filter: RenderToTextureFilter::Box,
+            depth: None,
+            normals: false,
+            motion_vectors: false,
+            mipmaps: false,
+            sampler: RenderToTextureSampler::Auto,
+            layout: RenderToTextureLayout::Single,
+        }
+    }
+}
+
+impl RenderToTexture {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: UVec2::new(width, height),
+            ..default()
+        }
+    }
+
+    /// The size of the render target, i.e., `size` multiplied by the supersampling factor.
+    pub fn render_size(&self) -> UVec2 {
+        self.size * self.supersampling.max(1)
+    }
+
+    /// The outputs besides the color image that are read back.
+    pub fn extra_outputs(&self) -> Vec<RenderToTextureOutput> {
+        [
+            (self.depth.is_some(), RenderToTextureOutput::Depth),
+            (self.normals, RenderToTextureOutput::Normal),
+            (self.motion_vectors, RenderToTextureOutput::MotionVectors),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, output)| enabled.then_some(output))
+        .collect()
+    }
+
+    /// Number of mip levels of the color image, not counting the ones basis compression generates.
+    pub fn mip_level_count(&self) -> u32 {
+        if self.mipmaps && !self.compresses_to_basis() {
+            resample::mip_level_count(self.size)
+        } else {
+            1
+        }
+    }
+
+    /// Whether the result is compressed with basis universal.
+    pub fn compresses_to_basis(&self) -> bool {
+        self.compress && self.compressor == RenderToTextureCompressor::Basis
+    }
+
+    /// The camera kind and transform rendering each layer of the texture.
+    pub fn layer_cameras(&self) -> Vec<(RenderToTextureCamera, Option<Transform>)> {
+        match &self.layout {
+            RenderToTextureLayout::Single => vec![(self.camera, self.transform)],
+            RenderToTextureLayout::Cube => {
+                let (near, far) = match self.camera {
+                    RenderToTextureCamera::Perspective { near, far, .. } => (near, far),
+                    _ => {
+                        let projection = PerspectiveProjection::default();
+                        (projection.near, projection.far)
+                    }
+                };
+                let camera = RenderToTextureCamera::Perspective {
+                    fov: FRAC_PI_2,
+                    near,
+                    far,
+                };
+                let base = self.transform.unwrap_or_default();
+                CUBE_FACES
+                    .iter()
+                    .map(|(forward, up)| {
+                        let face = Transform::IDENTITY.looking_to(*forward, *up);
+                        let transform = Transform {
+                            rotation: base.rotation * face.rotation,
+                            ..base
+                        };
+                        (camera, Some(transform))
+                    })
+                    .collect()
+            }
+            RenderToTextureLayout::Array(transforms) => transforms
+                .iter()
+                .map(|transform| (self.camera, Some(*transform)))
+                .collect(),
+            // the cameras of the regions are spawned by the atlas
+            #[cfg(feature = "atlas")]
+            RenderToTextureLayout::Atlas { .. } | RenderToTextureLayout::AtlasRegion(_) => {
+                Vec::new()
+            }
+        }
+    }
+
+    /// The format of the render target, i.e., `format` adjusted to the `color_space` if one is set.
+    /// Formats without an sRGB variant are used as they are.
+    pub fn target_format(&self) -> TextureFormat {
+        match self.color_space {
+            None => self.format,
+            Some(RenderToTextureColorSpace::Srgb) => self.format.add_srgb_suffix(),
+            Some(RenderToTextureColorSpace::Linear) => self.format.remove_srgb_suffix(),
+        }
+    }
+}
+
+/// The rendered image of a [`RenderToTexture`] entity. Replaced whenever the texture was rendered again.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct RenderToTextureResult {
+    pub image: Handle<Image>,
+    /// Additional images like the depth buffer.
+    pub outputs: HashMap<RenderToTextureOutput, Handle<Image>>,
+    /// The regions of an atlas, see [`RenderToTextureLayout::Atlas`].
+    #[cfg(feature = "atlas")]
+    pub atlas: Option<RenderToTextureAtlas>,
+}
+
+/// The regions of the result of an atlas task.
+#[cfg(feature = "atlas")]
+#[derive(Clone, Debug, Default, Reflect)]
+pub struct RenderToTextureAtlas {
+    pub layout: Handle<TextureAtlasLayout>,
+    /// The index of the texture of each region task in the layout.
+    pub regions: HashMap<RenderToTextureTaskId, usize>,
+}
+
+/// Creates tasks for new `RenderToTexture` components and renders them again when they change.
+pub fn spawn_render_to_texture_tasks(
+    mut commands: Commands,
+    mut tasks: ResMut<RenderToTextureTasks>,
+    mut images: ResMut<Assets<Image>>,
+    query: Query<
+        (
+            Entity,
+            &RenderToTexture,
+            Option<&Name>,
+            Option<&TaskResource>,
+            Has<GlobalTransform>,
+        ),
+        Changed<RenderToTexture>,
+    >,
+) {
+    for (entity, settings, name, task, has_transform) in &query {
+        if let Some(TaskResource(id)) = task {
+            if let Some(existing) = tasks.get_mut(*id) {
+                if existing.settings() == settings {
+                    existing.rerender();
+                    continue;
+                }
+            }
+            tasks.free(*id, &mut commands);
+        }
+
+        let id = tasks.add(
+            name.map(Name::as_str),
+            settings.clone(),
+            &mut commands,
+            &mut images,
+        );
+        tasks.set_owner(id, entity);
+
+        let mut owner = commands.entity(entity);
+        owner.insert(TaskResource(id));
+        let task = tasks.get(id).filter(|task| task.owns_camera());
+        let cameras = task.map_or(&[][..], |task| task.cameras());
+        if !cameras.is_empty() {
+            // the cameras' transforms are relative to the owner
+            if !has_transform {
+                owner.insert(TransformBundle::default());
+            }
+            owner.push_children(cameras);
+        }
+    }
+}
+
+/// Frees the tasks of despawned or removed `RenderToTexture` components.
+pub fn despawn_render_to_texture_tasks(
+    mut commands: Commands,
+    mut tasks: ResMut<RenderToTextureTasks>,
+    mut removed: RemovedComponents<RenderToTexture>,
+) {
+    for entity in removed.read() {
+        if let Some(id) = tasks.find_owned_by(entity) {
+            tasks.free(id, &mut commands);
+        }
+        if let Some(mut owner) = commands.get_entity(entity) {
+            owner.remove::<TaskResource>();
+        }
+    }
+}
+
+/// Hands finished images to the entities owning the tasks.
+pub fn update_render_to_texture_results(
+    mut commands: Commands,
+    mut tasks: ResMut<RenderToTextureTasks>,
+    mut finished: EventReader<RenderToTextureFinished>,
+) {
+    for event in finished.read() {
+        let Some(task) = tasks.get(event.task) else {
+            continue;
+        };
+        let Some(owner) = task.owner() else {
+            continue;
+        };
+        let finish = task.settings().mode == RenderToTextureMode::Once;
+        tasks.mark_received(event.task, finish);
+        if let Some(mut owner) = commands.get_entity(owner) {
+            owner.insert(RenderToTextureResult {
+                image: event.image.clone(),
+                outputs: event.outputs.clone(),
+                #[cfg(feature = "atlas")]
+                atlas: event.atlas.clone(),
+            });
+        }
+    }
+}
@@ -1,25 +1,46 @@
+use crate::{BasisCompressionFormat, BasisCompressionSettings};
 use bevy::prelude::*;
 
-pub fn compress_to_basis_raw(data: &Vec<u8>, size: UVec2, is_srgb: bool) -> Vec<u8> {
+/// Compresses RGBA8 data to a basis file.
+///
+/// `CompressorParams` and `Compressor` are not `Send`, so they only live inside this function.
+/// Call it from within a task to compress in the background.
+pub fn compress_to_basis_raw(
+    data: &[u8],
+    size: UVec2,
+    is_srgb: bool,
+    settings: &BasisCompressionSettings,
+) -> Vec<u8> {
     // from bevy::render::texture::CompressedImageSaver:
 
-    // PERF: this should live inside the future, but CompressorParams and Compressor are not Send / can't be owned by the BoxedFuture (which _is_ Send)
     let mut compressor_params = basis_universal::CompressorParams::new();
-    compressor_params.set_basis_format(basis_universal::BasisTextureFormat::UASTC4x4);
-    compressor_params.set_generate_mipmaps(true);
+    match settings.format {
+        BasisCompressionFormat::Etc1s { quality } => {
+            compressor_params.set_basis_format(basis_universal::BasisTextureFormat::ETC1S);
+            compressor_params.set_etc1s_quality_level(quality);
+        }
+        BasisCompressionFormat::Uastc { quality, rdo } => {
+            compressor_params.set_basis_format(basis_universal::BasisTextureFormat::UASTC4x4);
+            compressor_params.set_uastc_quality_level(quality);
+            compressor_params.set_rdo_uastc(rdo);
+        }
+    }
+    compressor_params.set_generate_mipmaps(settings.mipmaps);
+    compressor_params.set_mipmap_smallest_dimension(settings.mipmap_smallest_dimension);
     let color_space = if is_srgb {
         basis_universal::ColorSpace::Srgb
     } else {
         basis_universal::ColorSpace::Linear
     };
     compressor_params.set_color_space(color_space);
-    compressor_params.set_uastc_quality_level(basis_universal::UASTC_QUALITY_DEFAULT);
-    compressor_params.set_etc1s_quality_level(basis_universal::ETC1S_QUALITY_DEFAULT);
+    if settings.normal_map {
+        compressor_params.tune_for_normal_maps();
+    }
 
     let mut source_image = compressor_params.source_image_mut(0);
     source_image.init(data, size.x, size.y, 4);
 
-    let mut compressor = basis_universal::Compressor::new(4);
+    let mut compressor = basis_universal::Compressor::new(settings.threads);
     // SAFETY: the CompressorParams are "valid" to the best of our knowledge. The basis-universal
     // library bindings note that invalid params might produce undefined behavior.
     unsafe {
@@ -39,5 +60,6 @@ pub fn compress_to_basis(image: &Image) -> Vec<u8> {
         &image.data,
         image.size(),
         image.texture_descriptor.format.is_srgb(),
+        &BasisCompressionSettings::default(),
     )
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+use crate::component::RenderToTextureOutput;
+use bevy::render::render_resource::TextureFormat;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderToTextureError {
+    /// Compression was requested but the feature of its backend, `compress` or `compress-bcn`, is not enabled.
+    CompressionUnavailable,
+    /// A KTX2 container or zstd supercompression was requested but the `ktx2` or `zstd` feature is not enabled.
+    ContainerUnavailable,
+    /// The data read back from the GPU couldn't be turned into an image.
+    InvalidImage(String),
+    /// The task was removed before it produced an image.
+    Cancelled,
+    /// The format can't be rendered to or is incompatible with the requested post-processing.
+    UnsupportedFormat(TextureFormat),
+    /// An output like the depth buffer couldn't be read back. It requires a 3d camera.
+    OutputUnavailable(RenderToTextureOutput),
+    /// An output like the depth buffer was requested while the `Msaa` resource is enabled, which it
+    /// is by default. Multisampled textures can't be read back, so insert `Msaa::Off`, e.g., using
+    /// `app.insert_resource(Msaa::Off)`. The task fails right away when it is added.
+    MultisampledOutput(RenderToTextureOutput),
+    /// The settings contradict each other.
+    InvalidSettings(String),
+    /// All render layers are in use by other tasks or reserved. Free a task to get its layer back.
+    NoFreeLayer,
+}
+
+impl fmt::Display for RenderToTextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CompressionUnavailable => write!(f, "The compression backend is not enabled"),
+            Self::ContainerUnavailable => write!(f, "The KTX2 container is not enabled"),
+            Self::InvalidImage(e) => write!(f, "Invalid image: {}", e),
+            Self::Cancelled => write!(f, "The task was removed before it finished"),
+            Self::UnsupportedFormat(format) => write!(f, "Unsupported format {:?}", format),
+            Self::OutputUnavailable(output) => write!(f, "{:?} couldn't be read back", output),
+            Self::MultisampledOutput(output) => write!(
+                f,
+                "{:?} can't be read back while the Msaa resource is enabled",
+                output
+            ),
+            Self::InvalidSettings(e) => write!(f, "Invalid settings: {}", e),
+            Self::NoFreeLayer => write!(f, "No free render layer left for the task"),
+        }
+    }
+}
+
+impl std::error::Error for RenderToTextureError {}
@@ -0,0 +1,48 @@
+#[cfg(feature = "atlas")]
+use crate::component::RenderToTextureAtlas;
+use crate::{
+    component::RenderToTextureOutput,
+    error::RenderToTextureError,
+    render::{RenderToTextureTaskId, RenderToTextureTasks},
+};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Sent once a task reached `ReadyForReading`. The image is also available via `RenderToTextureTask::result`.
+#[derive(Event, Clone, Debug)]
+pub struct RenderToTextureFinished {
+    pub task: RenderToTextureTaskId,
+    pub image: Handle<Image>,
+    /// Additional images like the depth buffer.
+    pub outputs: HashMap<RenderToTextureOutput, Handle<Image>>,
+    /// The regions of an atlas task.
+    #[cfg(feature = "atlas")]
+    pub atlas: Option<RenderToTextureAtlas>,
+}
+
+/// Sent when a task couldn't produce an image.
+#[derive(Event, Clone, Debug)]
+pub struct RenderToTextureFailed {
+    pub task: RenderToTextureTaskId,
+    pub error: RenderToTextureError,
+}
+
+pub fn send_render_to_texture_events(
+    mut tasks: ResMut<RenderToTextureTasks>,
+    mut images: ResMut<Assets<Image>>,
+    #[cfg(feature = "atlas")] mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut finished: EventWriter<RenderToTextureFinished>,
+    mut failed: EventWriter<RenderToTextureFailed>,
+) {
+    #[cfg(feature = "atlas")]
+    tasks.create_atlas_layouts(&mut atlas_layouts);
+    for result in tasks.collect_results(&mut images) {
+        match result {
+            Ok(event) => {
+                finished.send(event);
+            }
+            Err((task, error)) => {
+                failed.send(RenderToTextureFailed { task, error });
+            }
+        }
+    }
+}
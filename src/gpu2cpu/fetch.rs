@@ -1,21 +1,26 @@
 // based on https://github.com/paulkre/bevy_image_export/blob/main/src/node.rs
 
 //use crate::compress::compress_to_basis_raw;
-use super::source::ImageExportSource;
+use super::{source::ImageExportSource, view::ViewTextureBuffers, ExportId};
+use crate::component::RenderToTextureOutput;
 use bevy::{
     ecs::query::WorldQuery,
     prelude::*,
     render::{
         extract_component::ExtractComponent,
         render_asset::RenderAssets,
-        render_resource::{Maintain, MapMode},
+        render_resource::{Buffer, Maintain, MapMode},
         renderer::RenderDevice,
     },
+    utils::{HashMap, HashSet},
 };
 use futures::channel::oneshot;
 
-#[derive(Asset, Clone, Default, Reflect, Component)]
-pub struct ImageExportSettings;
+#[derive(Asset, Clone, Reflect, Component)]
+pub struct ImageExportSettings {
+    /// The owner of the exported image data.
+    pub id: ExportId,
+}
 
 impl ExtractComponent for ImageExportSettings {
     type QueryData = (&'static Self, &'static Handle<ImageExportSource>);
@@ -29,7 +34,7 @@ impl ExtractComponent for ImageExportSettings {
     }
 }
 
-#[derive(Bundle, Default)]
+#[derive(Bundle)]
 pub struct ImageExportBundle {
     pub source: Handle<ImageExportSource>,
     pub settings: ImageExportSettings,
@@ -37,68 +42,96 @@ pub struct ImageExportBundle {
 
 #[derive(Resource, Clone, Default, Reflect)]
 pub struct ExtractableImages {
-    pub raw: Vec<u8>,
-    pub refresh: bool,
+    /// The image data read back from the GPU per export and output.
+    pub raw: HashMap<(ExportId, RenderToTextureOutput), Vec<u8>>,
+    /// The exports that are currently waiting for image data.
+    #[reflect(ignore)]
+    pub refresh: HashSet<ExportId>,
+}
+
+/// Maps the buffer and returns its content without the row padding.
+fn read_buffer(
+    render_device: &RenderDevice,
+    buffer: &Buffer,
+    bytes_per_row: usize,
+    padded_bytes_per_row: usize,
+) -> Vec<u8> {
+    let mut image_bytes = {
+        let slice = buffer.slice(..);
+        {
+            let (mapping_tx, mapping_rx) = oneshot::channel();
+            render_device.map_buffer(&slice, MapMode::Read, move |res| {
+                mapping_tx.send(res).unwrap();
+            });
+            render_device.poll(Maintain::Wait);
+            futures_lite::future::block_on(mapping_rx).unwrap().unwrap();
+        }
+        slice.get_mapped_range().to_vec()
+    };
+
+    buffer.unmap();
+
+    if bytes_per_row != padded_bytes_per_row {
+        let mut unpadded_bytes =
+            Vec::<u8>::with_capacity(image_bytes.len() / padded_bytes_per_row * bytes_per_row);
+        for padded_row in image_bytes.chunks(padded_bytes_per_row) {
+            unpadded_bytes.extend_from_slice(&padded_row[..bytes_per_row]);
+        }
+        image_bytes = unpadded_bytes;
+    }
+    image_bytes
 }
 
 pub fn store_in_img(
     export_bundles: Query<(&Handle<ImageExportSource>, &ImageExportSettings)>,
     sources: Res<RenderAssets<ImageExportSource>>,
     render_device: Res<RenderDevice>,
-    mut extractable_image: ResMut<ExtractableImages>,
+    mut extractable_images: ResMut<ExtractableImages>,
+    view_buffers: Res<ViewTextureBuffers>,
     //mut gpu_images: ResMut<RenderAssets<Image>>,
 ) {
-    let mut copied = false;
-    for (source_handle, _) in &export_bundles {
+    for (source_handle, settings) in &export_bundles {
+        let id = settings.id;
+        if !extractable_images.refresh.contains(&id) || view_buffers.pending.contains(&id) {
+            continue;
+        }
         if let Some(gpu_source) = sources.get(source_handle) {
-            let mut image_bytes = {
-                let slice = gpu_source.buffer.slice(..);
-                {
-                    let (mapping_tx, mapping_rx) = oneshot::channel();
-                    render_device.map_buffer(&slice, MapMode::Read, move |res| {
-                        mapping_tx.send(res).unwrap();
-                    });
-                    render_device.poll(Maintain::Wait);
-                    futures_lite::future::block_on(mapping_rx).unwrap().unwrap();
-                }
-                slice.get_mapped_range().to_vec()
-            };
-
-            gpu_source.buffer.unmap();
+            let image_bytes = read_buffer(
+                &render_device,
+                &gpu_source.buffer,
+                gpu_source.bytes_per_row as usize,
+                gpu_source.padded_bytes_per_row as usize,
+            );
 
-            let bytes_per_row = gpu_source.bytes_per_row as usize;
-            let padded_bytes_per_row = gpu_source.padded_bytes_per_row as usize;
-            let source_size = gpu_source.source_size;
-            if bytes_per_row != padded_bytes_per_row {
-                let mut unpadded_bytes =
-                    Vec::<u8>::with_capacity(source_size.height as usize * bytes_per_row);
-                for padded_row in image_bytes.chunks(padded_bytes_per_row) {
-                    unpadded_bytes.extend_from_slice(&padded_row[..bytes_per_row]);
-                }
-                image_bytes = unpadded_bytes;
-            }
+            /*let gpu_image = gpu_images.get_mut(&gpu_source.source_handle).unwrap();
+            let width = gpu_image.size.x as u32;
+            let height = gpu_image.size.y as u32;
+            let mut writer =
+                std::io::BufWriter::new(std::fs::File::create("test.png").unwrap());
+            image::write_buffer_with_format(
+                &mut writer,
+                &image_bytes,
+                height,
+                width,
+                image::ColorType::Rgba8,
+                image::ImageFormat::Png,
+            )
+            .unwrap();*/
 
-            if extractable_image.refresh && extractable_image.raw != image_bytes {
-                assert!(copied == false, "Image data was copied twice");
-                extractable_image.raw = image_bytes.clone();
-                //println!("Image data copied");
-                copied = true;
-
-                /*let gpu_image = gpu_images.get_mut(&gpu_source.source_handle).unwrap();
-                let width = gpu_image.size.x as u32;
-                let height = gpu_image.size.y as u32;
-                let mut writer =
-                    std::io::BufWriter::new(std::fs::File::create("test.png").unwrap());
-                image::write_buffer_with_format(
-                    &mut writer,
-                    &image_bytes,
-                    height,
-                    width,
-                    image::ColorType::Rgba8,
-                    image::ImageFormat::Png,
-                )
-                .unwrap();*/
-            }
+            //println!("Image data copied");
+            extractable_images
+                .raw
+                .insert((settings.id, RenderToTextureOutput::Color), image_bytes);
         }
     }
+
+    for (key, view_buffer) in &view_buffers.buffers {
+        let bytes = read_buffer(
+            &render_device,
+            &view_buffer.buffer,
+            view_buffer.bytes_per_row as usize,
+            view_buffer.padded_bytes_per_row as usize,
+        );
+        extractable_images.raw.insert(*key, bytes);
+    }
 }
@@ -14,13 +14,20 @@ use fetch::store_in_img;
 pub use fetch::{ExtractableImages, ImageExportBundle, ImageExportSettings};
 use node::{ImageExportNode, ImageExportRenderLabel};
 pub use source::ImageExportSource;
+pub use view::ViewTextureExport;
+use view::{prepare_view_texture_buffers, ViewTextureBuffers};
 mod fetch;
 mod node;
 mod source;
+mod view;
 
 #[derive(Default)]
 pub struct ImageExportPlugin {}
 
+/// Identifies the owner of exported image data, e.g., a render to texture task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub struct ExportId(pub u64);
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub enum ImageExportSystems {
     SetupImageExport,
@@ -28,25 +35,23 @@ pub enum ImageExportSystems {
 }
 
 pub fn sync_images(mut render_world_data: ResMut<ExtractableImages>, mut world: ResMut<MainWorld>) {
-
-
     let mut main_world_data = world.get_resource_mut::<ExtractableImages>().unwrap();
-    render_world_data.refresh = main_world_data.refresh;
-
-    if render_world_data.raw.is_empty() {
-        return;
-    }
-
-    // wait for the main world to eat the previous changes
-    if !main_world_data.raw.is_empty() {
-        return;
-    }
+    render_world_data.refresh = main_world_data.refresh.clone();
 
     // println!("sync_images");
 
-    main_world_data.raw = render_world_data.raw.clone();
-    render_world_data.raw.clear();
-    main_world_data.refresh = false;
+    let render_world_data = render_world_data.as_mut();
+    render_world_data.raw.retain(|key, raw| {
+        // wait for the main world to eat the previous changes
+        if main_world_data.raw.contains_key(key) {
+            return true;
+        }
+
+        // the data arrived, so don't copy it again
+        render_world_data.refresh.remove(&key.0);
+        main_world_data.raw.insert(*key, std::mem::take(raw));
+        false
+    });
 }
 
 impl Plugin for ImageExportPlugin {
@@ -62,23 +67,29 @@ impl Plugin for ImageExportPlugin {
         .register_type::<ImageExportSource>()
         .init_asset::<ImageExportSource>()
         .register_asset_reflect::<ImageExportSource>()
+        .register_type::<ExportId>()
         .register_type::<ExtractableImages>()
         .insert_resource(ExtractableImages::default())
         .add_plugins((
             RenderAssetPlugin::<ImageExportSource>::default(),
             ExtractComponentPlugin::<ImageExportSettings>::default(),
+            ExtractComponentPlugin::<ViewTextureExport>::default(),
         ))
         .add_systems(PostUpdate, apply_deferred.in_set(SetupImageExportFlush));
 
         let render_app = app.sub_app_mut(RenderApp);
         render_app
             .init_resource::<ExtractableImages>()
+            .init_resource::<ViewTextureBuffers>()
             .add_systems(ExtractSchedule, sync_images)
             .add_systems(
                 Render,
-                store_in_img
-                    .after(RenderSet::Render)
-                    .before(RenderSet::Cleanup),
+                (
+                    prepare_view_texture_buffers.in_set(RenderSet::PrepareBindGroups),
+                    store_in_img
+                        .after(RenderSet::Render)
+                        .before(RenderSet::Cleanup),
+                ),
             );
 
         let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
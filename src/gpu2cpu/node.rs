@@ -1,12 +1,15 @@
 // based on https://github.com/paulkre/bevy_image_export/blob/main/src/node.rs
 
-use super::source::ImageExportSource;
+use super::{source::ImageExportSource, view::ViewTextureBuffers};
 use bevy::{
     prelude::*,
     render::{
         render_asset::RenderAssets,
         render_graph::{Node, NodeRunError, RenderGraphContext, RenderLabel},
-        render_resource::{ImageCopyBuffer, ImageDataLayout},
+        render_resource::{
+            ImageCopyBuffer, ImageDataLayout, Operations, PipelineCache, RenderPassColorAttachment,
+            RenderPassDescriptor,
+        },
         renderer::RenderContext,
     },
 };
@@ -24,16 +27,16 @@ impl Node for ImageExportNode {
     ) -> Result<(), NodeRunError> {
         for (_, source) in world.resource::<RenderAssets<ImageExportSource>>().iter() {
             // TODO: only copy when the source has changed
-            if let Some(gpu_image) = world
-                .resource::<RenderAssets<Image>>()
-                .get(&source.source_handle)
-            {
+            for (layer, handle) in source.source_handles.iter().enumerate() {
+                let Some(gpu_image) = world.resource::<RenderAssets<Image>>().get(handle) else {
+                    continue;
+                };
                 render_context.command_encoder().copy_texture_to_buffer(
                     gpu_image.texture.as_image_copy(),
                     ImageCopyBuffer {
                         buffer: &source.buffer,
                         layout: ImageDataLayout {
-                            offset: 0,
+                            offset: source.layer_offset(layer),
                             bytes_per_row: Some(source.padded_bytes_per_row),
                             rows_per_image: None,
                         },
@@ -43,6 +46,44 @@ impl Node for ImageExportNode {
             }
         }
 
+        for view_buffer in world.resource::<ViewTextureBuffers>().buffers.values() {
+            if let Some(blit) = &view_buffer.blit {
+                let Some(pipeline) = world
+                    .resource::<PipelineCache>()
+                    .get_render_pipeline(blit.pipeline)
+                else {
+                    continue;
+                };
+                let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                    label: Some("view_texture_export_blit"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &blit.target,
+                        resolve_target: None,
+                        ops: Operations::default(),
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                pass.set_render_pipeline(pipeline);
+                pass.set_bind_group(0, &blit.bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+
+            render_context.command_encoder().copy_texture_to_buffer(
+                view_buffer.texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &view_buffer.buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(view_buffer.padded_bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                view_buffer.size,
+            );
+        }
+
         Ok(())
     }
 }
@@ -12,21 +12,34 @@ use bevy::{
 
 #[derive(Asset, Clone, Default, Reflect)]
 pub struct ImageExportSource {
-    pub image: Handle<Image>,
+    /// The images to copy, one after another like the layers of an array texture.
+    /// All images must have the same size and format.
+    pub images: Vec<Handle<Image>>,
 }
 
 impl From<Handle<Image>> for ImageExportSource {
     fn from(value: Handle<Image>) -> Self {
-        Self { image: value }
+        Self {
+            images: vec![value],
+        }
     }
 }
 
 pub struct GpuImageExportSource {
     pub buffer: Buffer,
-    pub source_handle: Handle<Image>,
+    pub source_handles: Vec<Handle<Image>>,
     pub source_size: Extent3d,
     pub bytes_per_row: u32,
     pub padded_bytes_per_row: u32,
+    /// Number of rows of texel blocks.
+    pub rows: u32,
+}
+
+impl GpuImageExportSource {
+    /// Offset of a layer in the buffer.
+    pub fn layer_offset(&self, layer: usize) -> u64 {
+        layer as u64 * self.rows as u64 * self.padded_bytes_per_row as u64
+    }
 }
 
 impl RenderAsset for ImageExportSource {
@@ -41,28 +54,35 @@ impl RenderAsset for ImageExportSource {
         self,
         (device, images): &mut SystemParamItem<Self::Param>,
     ) -> Result<Self::PreparedAsset, PrepareAssetError<Self>> {
-        let gpu_image = images.get(&self.image).unwrap();
+        let Some(gpu_image) = self.images.first().and_then(|image| images.get(image)) else {
+            return Err(PrepareAssetError::RetryNextUpdate(self));
+        };
 
-        let size = gpu_image.texture.size();
+        let source_size = gpu_image.texture.size();
         let format = &gpu_image.texture_format;
-        let bytes_per_row =
-            (size.width / format.block_dimensions().0) * format.block_copy_size(None).unwrap();
+
+        // a row of texel blocks, e.g., 4 pixel rows of a compressed format
+        let (block_width, block_height) = format.block_dimensions();
+        let bytes_per_row = source_size.width.div_ceil(block_width)
+            * format
+                .block_copy_size(None)
+                .expect("The format can't be copied to a buffer");
         let padded_bytes_per_row =
             RenderDevice::align_copy_bytes_per_row(bytes_per_row as usize) as u32;
-
-        let source_size = gpu_image.texture.size();
+        let rows = source_size.height.div_ceil(block_height);
 
         Ok(GpuImageExportSource {
             buffer: device.create_buffer(&BufferDescriptor {
                 label: Some("Image Export Buffer"),
-                size: (source_size.height * padded_bytes_per_row) as u64,
+                size: (self.images.len() as u32 * rows * padded_bytes_per_row) as u64,
                 usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
                 mapped_at_creation: false,
             }),
-            source_handle: self.image,
+            source_handles: self.images,
             source_size,
             bytes_per_row,
             padded_bytes_per_row,
+            rows,
         })
     }
 }
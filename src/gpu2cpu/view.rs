@@ -0,0 +1,199 @@
+use super::{ExportId, ExtractableImages};
+use crate::component::RenderToTextureOutput;
+use bevy::{
+    core_pipeline::{
+        blit::{BlitPipeline, BlitPipelineKey},
+        prepass::ViewPrepassTextures,
+    },
+    ecs::query::WorldQuery,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages,
+            CachedRenderPipelineId, Extent3d, PipelineCache, SpecializedRenderPipelines, Texture,
+            TextureDescriptor, TextureDimension, TextureUsages, TextureView,
+        },
+        renderer::RenderDevice,
+        texture::TextureCache,
+        view::ViewDepthTexture,
+    },
+    utils::{HashMap, HashSet},
+};
+
+/// Requests textures of a camera's view, like its depth buffer, to be read back.
+/// Add it to the camera of a task.
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct ViewTextureExport {
+    pub id: ExportId,
+    pub outputs: Vec<RenderToTextureOutput>,
+}
+
+impl ExtractComponent for ViewTextureExport {
+    type QueryData = &'static Self;
+    type QueryFilter = ();
+    type Out = Self;
+
+    fn extract_component(export: <Self::QueryData as WorldQuery>::Item<'_>) -> Option<Self::Out> {
+        Some(export.clone())
+    }
+}
+
+/// Renders a texture that can't be copied to a buffer into one that can.
+pub struct ViewTextureBlit {
+    pub pipeline: CachedRenderPipelineId,
+    pub bind_group: BindGroup,
+    pub target: TextureView,
+}
+
+/// The buffer a view texture is copied to.
+pub struct ViewTextureBuffer {
+    /// The texture that is copied to the buffer.
+    pub texture: Texture,
+    /// Fills `texture` before it is copied.
+    pub blit: Option<ViewTextureBlit>,
+    pub buffer: Buffer,
+    pub size: Extent3d,
+    pub bytes_per_row: u32,
+    pub padded_bytes_per_row: u32,
+}
+
+/// Buffers of the view textures that are read back in this frame.
+#[derive(Resource, Default)]
+pub struct ViewTextureBuffers {
+    pub buffers: HashMap<(ExportId, RenderToTextureOutput), ViewTextureBuffer>,
+    /// Exports that can't be read back in this frame, e.g., because a pipeline is still compiling.
+    /// None of their outputs are delivered, so all outputs of an export arrive together.
+    pub pending: HashSet<ExportId>,
+}
+
+/// Creates the buffers for the views of tasks that are waiting for data.
+///
+/// Outputs that can't be copied, e.g., because the texture is multisampled, are delivered
+/// as empty data so the task doesn't wait forever.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_view_texture_buffers(
+    views: Query<(
+        &ViewTextureExport,
+        Option<&ViewDepthTexture>,
+        Option<&ViewPrepassTextures>,
+    )>,
+    device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    pipeline_cache: Res<PipelineCache>,
+    blit_pipeline: Res<BlitPipeline>,
+    mut blit_pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    mut buffers: ResMut<ViewTextureBuffers>,
+    mut extractable_images: ResMut<ExtractableImages>,
+) {
+    let ViewTextureBuffers { buffers, pending } = buffers.as_mut();
+    buffers.clear();
+    pending.clear();
+
+    for (export, depth, prepass) in &views {
+        if !extractable_images.refresh.contains(&export.id) {
+            continue;
+        }
+        for output in &export.outputs {
+            let (texture, needs_blit) = match output {
+                RenderToTextureOutput::Color => continue,
+                RenderToTextureOutput::Depth => (depth.map(|d| &d.texture), false),
+                RenderToTextureOutput::Normal => (
+                    prepass
+                        .and_then(|p| p.normal.as_ref())
+                        .map(|n| &n.texture.texture),
+                    true,
+                ),
+                RenderToTextureOutput::MotionVectors => (
+                    prepass
+                        .and_then(|p| p.motion_vectors.as_ref())
+                        .map(|m| &m.texture.texture),
+                    true,
+                ),
+            };
+            let Some(texture) = texture.filter(|t| t.sample_count() == 1) else {
+                extractable_images
+                    .raw
+                    .insert((export.id, *output), Vec::new());
+                continue;
+            };
+
+            let size = texture.size();
+            let format = texture.format();
+            let (texture, blit) = if needs_blit {
+                // prepass textures can't be copied, so render them into a texture that can
+                let pipeline = blit_pipelines.specialize(
+                    &pipeline_cache,
+                    &blit_pipeline,
+                    BlitPipelineKey {
+                        texture_format: format,
+                        blend_state: None,
+                        samples: 1,
+                    },
+                );
+                if pipeline_cache.get_render_pipeline(pipeline).is_none() {
+                    pending.insert(export.id);
+                    continue;
+                }
+
+                let target = texture_cache.get(
+                    &device,
+                    TextureDescriptor {
+                        label: Some("View Texture Export Target"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format,
+                        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                        view_formats: &[],
+                    },
+                );
+                let bind_group = device.create_bind_group(
+                    "view_texture_export_bind_group",
+                    &blit_pipeline.texture_bind_group,
+                    &BindGroupEntries::sequential((
+                        &texture.create_view(&Default::default()),
+                        &blit_pipeline.sampler,
+                    )),
+                );
+                let blit = ViewTextureBlit {
+                    pipeline,
+                    bind_group,
+                    target: target.default_view,
+                };
+                (target.texture, Some(blit))
+            } else {
+                (texture.clone(), None)
+            };
+
+            let bytes_per_row = size.width
+                * format
+                    .block_copy_size(None)
+                    .expect("The view texture can't be copied to a buffer");
+            let padded_bytes_per_row =
+                RenderDevice::align_copy_bytes_per_row(bytes_per_row as usize) as u32;
+            buffers.insert(
+                (export.id, *output),
+                ViewTextureBuffer {
+                    texture,
+                    blit,
+                    buffer: device.create_buffer(&BufferDescriptor {
+                        label: Some("View Texture Export Buffer"),
+                        size: (size.height * padded_bytes_per_row) as u64,
+                        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                        mapped_at_creation: false,
+                    }),
+                    size,
+                    bytes_per_row,
+                    padded_bytes_per_row,
+                },
+            );
+        }
+    }
+
+    buffers.retain(|(id, _), _| !pending.contains(id));
+    extractable_images
+        .raw
+        .retain(|(id, _), _| !pending.contains(id));
+}
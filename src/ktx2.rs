@@ -0,0 +1,704 @@
+//! Writes the results of tasks to KTX2 files that Bevy's `ktx2` loader reads.
+
+use crate::error::RenderToTextureError;
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+
+/// Identifier at the start of every KTX2 file.
+const MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+const HEADER_LENGTH: usize = 80;
+const LEVEL_INDEX_LENGTH: usize = 24;
+
+/// `supercompressionScheme` of Zstandard.
+const ZSTD: u32 = 2;
+
+/// Color models of the data format descriptor.
+const MODEL_RGBSDA: u32 = 1;
+const MODEL_BC1A: u32 = 128;
+const MODEL_BC3: u32 = 130;
+const MODEL_BC4: u32 = 131;
+const MODEL_BC5: u32 = 132;
+const MODEL_BC7: u32 = 134;
+const MODEL_UASTC: u32 = 166;
+
+/// Channels of the RGBSDA color model. Block compressed models number their channels from 0.
+const RED: u32 = 0;
+const GREEN: u32 = 1;
+const BLUE: u32 = 2;
+const ALPHA: u32 = 15;
+/// The BC1 channel that marks the 3 color mode as transparent.
+const BC1_ALPHA_PRESENT: u32 = 1;
+const UASTC_RGB: u32 = 0;
+const UASTC_RGBA: u32 = 3;
+
+/// Channel qualifiers of the samples.
+const LINEAR: u32 = 1;
+const SIGNED: u32 = 4;
+const FLOAT: u32 = 8;
+
+/// The data inside a KTX2 file.
+#[derive(Clone, Copy, Debug)]
+pub enum Ktx2Format {
+    /// Texel or block compressed data of a texture format.
+    Texture(TextureFormat),
+    /// The UASTC blocks of a basis file.
+    Basis,
+}
+
+/// Describes the texture that is written to a KTX2 file.
+#[derive(Clone, Copy, Debug)]
+pub struct Ktx2Texture {
+    pub format: Ktx2Format,
+    pub size: UVec2,
+    /// Mip levels of texture data. Basis files bring their own.
+    pub levels: u32,
+    /// Number of layers, or 6 for a cubemap.
+    pub layers: u32,
+    pub cube: bool,
+    /// Whether basis data is in sRGB space. Texture formats tell themselves.
+    pub srgb: bool,
+    /// Supercompress each mip level with zstd at this level.
+    pub zstd_level: Option<i32>,
+}
+
+impl Ktx2Texture {
+    /// Writes the data to a KTX2 file. Texture data is ordered layer by layer with the mip levels
+    /// of each layer, like wgpu expects it. Basis data is the basis file.
+    pub fn write(&self, data: &[u8]) -> Result<Vec<u8>, RenderToTextureError> {
+        let (levels, descriptor) = match self.format {
+            Ktx2Format::Texture(format) => {
+                let (vk_format, type_size, model, samples) =
+                    describe(format).ok_or(RenderToTextureError::UnsupportedFormat(format))?;
+                let descriptor = Descriptor {
+                    vk_format,
+                    type_size,
+                    model,
+                    srgb: format.is_srgb(),
+                    block: format.block_dimensions(),
+                    block_bytes: format.block_copy_size(None).unwrap_or(1),
+                    samples,
+                };
+                (self.texture_levels(data, &descriptor)?, descriptor)
+            }
+            Ktx2Format::Basis => {
+                let (levels, alpha) = uastc_levels(data)
+                    .map_err(|e| RenderToTextureError::InvalidImage(e.to_string()))?;
+                let channel = if alpha { UASTC_RGBA } else { UASTC_RGB };
+                let descriptor = Descriptor {
+                    // the format is undefined and described by the UASTC color model
+                    vk_format: 0,
+                    type_size: 1,
+                    model: MODEL_UASTC,
+                    srgb: self.srgb,
+                    block: (4, 4),
+                    block_bytes: 16,
+                    samples: vec![Sample::block(channel, 0, 128)],
+                };
+                (levels, descriptor)
+            }
+        };
+
+        let uncompressed_lengths: Vec<usize> = levels.iter().map(Vec::len).collect();
+        let levels = match self.zstd_level {
+            None => levels,
+            #[cfg(feature = "zstd")]
+            Some(zstd_level) => levels
+                .iter()
+                .map(|level| zstd::bulk::compress(level, zstd_level))
+                .collect::<Result<_, _>>()
+                .map_err(|e| RenderToTextureError::InvalidImage(e.to_string()))?,
+            #[cfg(not(feature = "zstd"))]
+            Some(_) => return Err(RenderToTextureError::ContainerUnavailable),
+        };
+
+        let dfd = descriptor.to_bytes();
+        let kvd = key_value_data();
+        let dfd_offset = HEADER_LENGTH + LEVEL_INDEX_LENGTH * levels.len();
+        let kvd_offset = dfd_offset + dfd.len();
+
+        let mut file = vec![0; kvd_offset];
+        file.extend(kvd.iter());
+
+        // supercompressed levels are byte streams, the others are aligned to their blocks
+        let alignment = if self.zstd_level.is_some() {
+            1
+        } else {
+            lcm(descriptor.block_bytes as usize, 4)
+        };
+        // the smallest level comes first
+        let mut level_index = vec![(0, 0, 0); levels.len()];
+        for (level, level_data) in levels.iter().enumerate().rev() {
+            file.resize(file.len().next_multiple_of(alignment), 0);
+            level_index[level] = (file.len(), level_data.len(), uncompressed_lengths[level]);
+            file.extend(level_data.iter());
+        }
+
+        let header = [
+            descriptor.vk_format,
+            descriptor.type_size,
+            self.size.x,
+            self.size.y,
+            // 2d textures have no depth, single textures and cubemaps are no arrays
+            0,
+            if self.cube || self.layers <= 1 {
+                0
+            } else {
+                self.layers
+            },
+            if self.cube { 6 } else { 1 },
+            levels.len() as u32,
+            if self.zstd_level.is_some() { ZSTD } else { 0 },
+            dfd_offset as u32,
+            dfd.len() as u32,
+            kvd_offset as u32,
+            kvd.len() as u32,
+        ];
+        file[..MAGIC.len()].copy_from_slice(&MAGIC);
+        for (i, value) in header.iter().enumerate() {
+            let offset = MAGIC.len() + i * 4;
+            file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        // there is no supercompression global data, so its offset and length stay 0
+
+        for (level, (offset, length, uncompressed_length)) in level_index.into_iter().enumerate() {
+            let start = HEADER_LENGTH + level * LEVEL_INDEX_LENGTH;
+            for (i, value) in [offset, length, uncompressed_length].iter().enumerate() {
+                file[start + i * 8..start + i * 8 + 8]
+                    .copy_from_slice(&(*value as u64).to_le_bytes());
+            }
+        }
+        file[dfd_offset..kvd_offset].copy_from_slice(&dfd);
+
+        Ok(file)
+    }
+
+    /// Regroups the data of each layer's mip chain into mip levels that contain every layer.
+    fn texture_levels(
+        &self,
+        data: &[u8],
+        descriptor: &Descriptor,
+    ) -> Result<Vec<Vec<u8>>, RenderToTextureError> {
+        let (block_width, block_height) = descriptor.block;
+        let level_lengths: Vec<usize> = (0..self.levels.max(1))
+            .map(|level| {
+                let size = (self.size >> level).max(UVec2::ONE);
+                let blocks_x = size.x.div_ceil(block_width);
+                let blocks_y = size.y.div_ceil(block_height);
+                (blocks_x * blocks_y * descriptor.block_bytes) as usize
+            })
+            .collect();
+        let layer_length: usize = level_lengths.iter().sum();
+        let layers = self.layers.max(1) as usize;
+        if data.len() != layer_length * layers {
+            return Err(RenderToTextureError::InvalidImage(format!(
+                "expected {} bytes for the KTX2 file, got {}",
+                layer_length * layers,
+                data.len()
+            )));
+        }
+
+        let mut levels = vec![Vec::new(); level_lengths.len()];
+        for layer in data.chunks_exact(layer_length) {
+            let mut offset = 0;
+            for (level, length) in levels.iter_mut().zip(&level_lengths) {
+                level.extend_from_slice(&layer[offset..offset + length]);
+                offset += length;
+            }
+        }
+        Ok(levels)
+    }
+}
+
+/// Whether the texture format can be written to a KTX2 file.
+pub fn is_supported(format: TextureFormat) -> bool {
+    describe(format).is_some()
+}
+
+/// A sample of the data format descriptor, i.e., the bits of a texel block holding a channel.
+struct Sample {
+    channel: u32,
+    offset: u32,
+    length: u32,
+    qualifiers: u32,
+    lower: u32,
+    upper: u32,
+}
+
+impl Sample {
+    fn unorm(channel: u32, offset: u32, length: u32) -> Self {
+        Self {
+            channel,
+            offset,
+            length,
+            qualifiers: 0,
+            lower: 0,
+            upper: (1 << length) - 1,
+        }
+    }
+
+    fn float(channel: u32, offset: u32, length: u32, signed: bool) -> Self {
+        Self {
+            channel,
+            offset,
+            length,
+            qualifiers: if signed { FLOAT | SIGNED } else { FLOAT },
+            lower: if signed { (-1.0f32).to_bits() } else { 0 },
+            upper: 1.0f32.to_bits(),
+        }
+    }
+
+    fn block(channel: u32, offset: u32, length: u32) -> Self {
+        Self {
+            channel,
+            offset,
+            length,
+            qualifiers: 0,
+            lower: 0,
+            upper: u32::MAX,
+        }
+    }
+}
+
+/// The basic data format descriptor and the format fields of the header.
+struct Descriptor {
+    vk_format: u32,
+    type_size: u32,
+    model: u32,
+    srgb: bool,
+    block: (u32, u32),
+    block_bytes: u32,
+    samples: Vec<Sample>,
+}
+
+impl Descriptor {
+    fn to_bytes(&self) -> Vec<u8> {
+        let block_size = 24 + 16 * self.samples.len() as u32;
+        // BT.709 primaries with a linear or sRGB transfer function
+        let transfer = if self.srgb { 2 } else { 1 };
+        let mut words = vec![
+            // total size of all descriptors
+            4 + block_size,
+            // vendor and type of the basic descriptor
+            0,
+            2 | (block_size << 16),
+            self.model | (1 << 8) | (transfer << 16),
+            (self.block.0 - 1) | ((self.block.1 - 1) << 8),
+            self.block_bytes,
+            0,
+        ];
+        for sample in &self.samples {
+            // alpha is linear even if the colors are sRGB encoded
+            let linear = if self.srgb && sample.channel == ALPHA {
+                LINEAR
+            } else {
+                0
+            };
+            words.extend([
+                sample.offset
+                    | ((sample.length - 1) << 16)
+                    | (sample.channel << 24)
+                    | ((sample.qualifiers | linear) << 28),
+                0,
+                sample.lower,
+                sample.upper,
+            ]);
+        }
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+}
+
+/// The Vulkan format, type size, color model and samples of a texture format.
+fn describe(format: TextureFormat) -> Option<(u32, u32, u32, Vec<Sample>)> {
+    let rgba8 = |order: [u32; 4]| {
+        order
+            .iter()
+            .enumerate()
+            .map(|(i, channel)| Sample::unorm(*channel, i as u32 * 8, 8))
+            .collect::<Vec<_>>()
+    };
+    let floats = |channels: &[u32], length: u32| {
+        channels
+            .iter()
+            .enumerate()
+            .map(|(i, channel)| Sample::float(*channel, i as u32 * length, length, true))
+            .collect::<Vec<_>>()
+    };
+    let (vk_format, type_size, model, samples) = match format {
+        TextureFormat::R8Unorm => (9, 1, MODEL_RGBSDA, vec![Sample::unorm(RED, 0, 8)]),
+        TextureFormat::Rg8Unorm => (
+            16,
+            1,
+            MODEL_RGBSDA,
+            vec![Sample::unorm(RED, 0, 8), Sample::unorm(GREEN, 8, 8)],
+        ),
+        TextureFormat::Rgba8Unorm => (37, 1, MODEL_RGBSDA, rgba8([RED, GREEN, BLUE, ALPHA])),
+        TextureFormat::Rgba8UnormSrgb => (43, 1, MODEL_RGBSDA, rgba8([RED, GREEN, BLUE, ALPHA])),
+        TextureFormat::Bgra8Unorm => (44, 1, MODEL_RGBSDA, rgba8([BLUE, GREEN, RED, ALPHA])),
+        TextureFormat::Bgra8UnormSrgb => (50, 1, MODEL_RGBSDA, rgba8([BLUE, GREEN, RED, ALPHA])),
+        TextureFormat::Rgb10a2Unorm => (
+            64,
+            4,
+            MODEL_RGBSDA,
+            vec![
+                Sample::unorm(RED, 0, 10),
+                Sample::unorm(GREEN, 10, 10),
+                Sample::unorm(BLUE, 20, 10),
+                Sample::unorm(ALPHA, 30, 2),
+            ],
+        ),
+        TextureFormat::R16Float => (76, 2, MODEL_RGBSDA, floats(&[RED], 16)),
+        TextureFormat::Rg16Float => (83, 2, MODEL_RGBSDA, floats(&[RED, GREEN], 16)),
+        TextureFormat::Rgba16Float => (97, 2, MODEL_RGBSDA, floats(&[RED, GREEN, BLUE, ALPHA], 16)),
+        TextureFormat::R32Float => (100, 4, MODEL_RGBSDA, floats(&[RED], 32)),
+        TextureFormat::Rg32Float => (103, 4, MODEL_RGBSDA, floats(&[RED, GREEN], 32)),
+        TextureFormat::Rgba32Float => {
+            (109, 4, MODEL_RGBSDA, floats(&[RED, GREEN, BLUE, ALPHA], 32))
+        }
+        TextureFormat::Rg11b10Float => (
+            122,
+            4,
+            MODEL_RGBSDA,
+            vec![
+                Sample::float(RED, 0, 11, false),
+                Sample::float(GREEN, 11, 11, false),
+                Sample::float(BLUE, 22, 10, false),
+            ],
+        ),
+        TextureFormat::Bc1RgbaUnorm | TextureFormat::Bc1RgbaUnormSrgb => (
+            if format.is_srgb() { 134 } else { 133 },
+            1,
+            MODEL_BC1A,
+            vec![Sample::block(BC1_ALPHA_PRESENT, 0, 64)],
+        ),
+        TextureFormat::Bc3RgbaUnorm | TextureFormat::Bc3RgbaUnormSrgb => (
+            if format.is_srgb() { 138 } else { 137 },
+            1,
+            MODEL_BC3,
+            vec![Sample::block(ALPHA, 0, 64), Sample::block(0, 64, 64)],
+        ),
+        TextureFormat::Bc4RUnorm => (139, 1, MODEL_BC4, vec![Sample::block(0, 0, 64)]),
+        TextureFormat::Bc5RgUnorm => (
+            141,
+            1,
+            MODEL_BC5,
+            vec![Sample::block(0, 0, 64), Sample::block(1, 64, 64)],
+        ),
+        TextureFormat::Bc7RgbaUnorm | TextureFormat::Bc7RgbaUnormSrgb => (
+            if format.is_srgb() { 146 } else { 145 },
+            1,
+            MODEL_BC7,
+            vec![Sample::block(0, 0, 128)],
+        ),
+        _ => return None,
+    };
+    Some((vk_format, type_size, model, samples))
+}
+
+/// Names the writer of the file, as recommended by the specification.
+fn key_value_data() -> Vec<u8> {
+    let entry = format!(
+        "KTXwriter\0{} {}\0",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+    let mut kvd = (entry.len() as u32).to_le_bytes().to_vec();
+    kvd.extend(entry.as_bytes());
+    kvd.resize(kvd.len().next_multiple_of(4), 0);
+    kvd
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    let gcd = |mut a: usize, mut b: usize| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    };
+    a / gcd(a, b) * b
+}
+
+/// The UASTC blocks of each mip level of a basis file and whether it has alpha.
+fn uastc_levels(basis: &[u8]) -> Result<(Vec<Vec<u8>>, bool), &'static str> {
+    // little endian integers of the basis file header and slice descriptions
+    let read = |offset: usize, length: usize| {
+        basis
+            .get(offset..offset + length)
+            .map(|bytes| bytes.iter().rev().fold(0, |v, b| (v << 8) | *b as usize))
+            .ok_or("the basis file is truncated")
+    };
+    if read(0, 2)? != 0x4273 {
+        return Err("not a basis file");
+    }
+    if read(20, 1)? != 1 {
+        return Err("only basis files with UASTC data can be written to KTX2");
+    }
+    let slice_count = read(14, 3)?;
+    let alpha = read(21, 2)? & 4 != 0;
+    let slice_descriptions = read(65, 4)?;
+
+    let mut slices = Vec::with_capacity(slice_count);
+    for slice in 0..slice_count {
+        let description = slice_descriptions + slice * 23;
+        let image = read(description, 3)?;
+        let level = read(description + 3, 1)?;
+        let (offset, length) = (read(description + 13, 4)?, read(description + 17, 4)?);
+        let data = basis
+            .get(offset..offset + length)
+            .ok_or("the basis file is truncated")?;
+        slices.push((level, image, data));
+    }
+    slices.sort_by_key(|(level, image, _)| (*level, *image));
+
+    let mut levels: Vec<Vec<u8>> = Vec::new();
+    for (level, _, data) in slices {
+        if levels.len() <= level {
+            levels.resize(level + 1, Vec::new());
+        }
+        levels[level].extend_from_slice(data);
+    }
+    if levels.is_empty() || levels.iter().any(Vec::is_empty) {
+        return Err("the basis file has missing mip levels");
+    }
+    Ok((levels, alpha))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ktx2::{
+        BasicDataFormatDescriptor, ChannelTypeQualifiers, ColorModel, ColorPrimaries, Format,
+        Reader, SampleInformation, SupercompressionScheme, TransferFunction,
+    };
+
+    fn texture(format: TextureFormat, size: UVec2, levels: u32, layers: u32) -> Ktx2Texture {
+        Ktx2Texture {
+            format: Ktx2Format::Texture(format),
+            size,
+            levels,
+            layers,
+            cube: false,
+            srgb: false,
+            zstd_level: None,
+        }
+    }
+
+    /// Data of the given levels of each layer, in the order `write` expects it.
+    /// Every byte depends on its layer, level and position.
+    fn layers(level_lengths: &[usize], layers: usize) -> Vec<Vec<Vec<u8>>> {
+        (0..layers)
+            .map(|layer| {
+                level_lengths
+                    .iter()
+                    .enumerate()
+                    .map(|(level, length)| {
+                        (0..*length)
+                            .map(|i| (layer * 101 + level * 37 + i * 7) as u8)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The byte offset, byte length and uncompressed byte length of a level.
+    fn level_index(file: &[u8], level: usize) -> (usize, usize, usize) {
+        let start = HEADER_LENGTH + level * LEVEL_INDEX_LENGTH;
+        let value = |i: usize| {
+            let bytes = &file[start + i * 8..start + i * 8 + 8];
+            u64::from_le_bytes(bytes.try_into().unwrap()) as usize
+        };
+        (value(0), value(1), value(2))
+    }
+
+    /// Writes the layers and checks that the levels contain the data of every layer in order.
+    fn write_and_read(texture: &Ktx2Texture, layers: &[Vec<Vec<u8>>]) -> Vec<u8> {
+        let data: Vec<u8> = layers.iter().flatten().flatten().copied().collect();
+        let file = texture.write(&data).unwrap();
+        let reader = Reader::new(&file).unwrap();
+        assert_eq!(reader.levels().len(), layers[0].len());
+        for (level, level_data) in reader.levels().enumerate() {
+            let expected: Vec<u8> = layers
+                .iter()
+                .flat_map(|levels| levels[level].iter().copied())
+                .collect();
+            let (offset, length, uncompressed_length) = level_index(&file, level);
+            assert_eq!(uncompressed_length, expected.len(), "level {level}");
+            assert_eq!(&file[offset..offset + length], level_data);
+            if texture.zstd_level.is_none() {
+                assert_eq!(level_data, expected, "level {level}");
+            }
+        }
+        file
+    }
+
+    /// The basic data format descriptor, the only one the files have.
+    fn descriptor<'a>(
+        reader: &'a Reader<&Vec<u8>>,
+    ) -> (BasicDataFormatDescriptor<'a>, Vec<SampleInformation>) {
+        let mut descriptors = reader.data_format_descriptors();
+        let basic = descriptors.next().unwrap();
+        assert!(descriptors.next().is_none());
+        assert_eq!(basic.header.vendor_id, 0);
+        assert_eq!(basic.header.descriptor_type, 0);
+        assert_eq!(basic.header.version_number, 2);
+        let basic = BasicDataFormatDescriptor::parse(basic.data).unwrap();
+        let samples = basic.sample_information().collect();
+        (basic, samples)
+    }
+
+    fn assert_levels_ordered(file: &[u8], levels: usize, alignment: usize) {
+        for level in 0..levels {
+            let (offset, _, _) = level_index(file, level);
+            assert_eq!(offset % alignment, 0, "level {level}");
+            // the smallest level comes first
+            if level > 0 {
+                assert!(offset < level_index(file, level - 1).0, "level {level}");
+            }
+        }
+    }
+
+    #[test]
+    fn rgba8_with_levels_and_layers() {
+        // 5x3, 2x1 and 1x1 pixels
+        let size = UVec2::new(5, 3);
+        let texture = texture(TextureFormat::Rgba8UnormSrgb, size, 3, 2);
+        let layers = layers(&[60, 8, 4], 2);
+        let file = write_and_read(&texture, &layers);
+        let reader = Reader::new(&file).unwrap();
+
+        let header = reader.header();
+        assert_eq!(header.format, Some(Format::R8G8B8A8_SRGB));
+        assert_eq!(header.type_size, 1);
+        assert_eq!((header.pixel_width, header.pixel_height), (5, 3));
+        assert_eq!(header.pixel_depth, 0);
+        assert_eq!(header.layer_count, 2);
+        assert_eq!(header.face_count, 1);
+        assert_eq!(header.level_count, 3);
+        assert_eq!(header.supercompression_scheme, None);
+        assert_levels_ordered(&file, 3, 4);
+
+        let (basic, samples) = descriptor(&reader);
+        assert_eq!(basic.color_model, Some(ColorModel::RGBSDA));
+        assert_eq!(basic.color_primaries, Some(ColorPrimaries::BT709));
+        assert_eq!(basic.transfer_function, Some(TransferFunction::SRGB));
+        assert_eq!(basic.texel_block_dimensions, [1; 4]);
+        assert_eq!(basic.bytes_planes, [4, 0, 0, 0, 0, 0, 0, 0]);
+        let channels = [RED, GREEN, BLUE, ALPHA];
+        assert_eq!(samples.len(), 4);
+        for (i, (sample, channel)) in samples.iter().zip(channels).enumerate() {
+            assert_eq!(sample.channel_type, channel);
+            assert_eq!(sample.bit_offset, i as u32 * 8);
+            assert_eq!(sample.bit_length, 8);
+            assert_eq!((sample.lower, sample.upper), (0, 255));
+            // only alpha is stored linearly in sRGB textures
+            let linear = channel == ALPHA;
+            assert_eq!(
+                sample.channel_type_qualifiers,
+                if linear {
+                    ChannelTypeQualifiers::LINEAR
+                } else {
+                    ChannelTypeQualifiers::empty()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn bc7_with_levels() {
+        // 8x8 pixels are 4 blocks, the smaller levels one block each
+        let texture = texture(TextureFormat::Bc7RgbaUnorm, UVec2::splat(8), 4, 1);
+        let layers = layers(&[64, 16, 16, 16], 1);
+        let file = write_and_read(&texture, &layers);
+        let reader = Reader::new(&file).unwrap();
+
+        let header = reader.header();
+        assert_eq!(header.format, Some(Format::BC7_UNORM_BLOCK));
+        assert_eq!(header.layer_count, 0);
+        assert_eq!(header.level_count, 4);
+        // levels are aligned to whole blocks
+        assert_levels_ordered(&file, 4, 16);
+
+        let (basic, samples) = descriptor(&reader);
+        assert_eq!(basic.color_model, Some(ColorModel::BC7));
+        assert_eq!(basic.transfer_function, Some(TransferFunction::Linear));
+        assert_eq!(basic.texel_block_dimensions, [4, 4, 1, 1]);
+        assert_eq!(basic.bytes_planes[0], 16);
+        assert_eq!(samples.len(), 1);
+        let sample = &samples[0];
+        assert_eq!((sample.channel_type, sample.bit_offset), (0, 0));
+        assert_eq!(sample.bit_length, 128);
+        assert_eq!((sample.lower, sample.upper), (0, u32::MAX));
+    }
+
+    #[test]
+    fn cubemap() {
+        let texture = Ktx2Texture {
+            cube: true,
+            ..texture(TextureFormat::Rgba16Float, UVec2::splat(2), 2, 6)
+        };
+        let layers = layers(&[32, 8], 6);
+        let file = write_and_read(&texture, &layers);
+        let reader = Reader::new(&file).unwrap();
+
+        let header = reader.header();
+        assert_eq!(header.format, Some(Format::R16G16B16A16_SFLOAT));
+        assert_eq!(header.type_size, 2);
+        // the faces are not an array
+        assert_eq!(header.layer_count, 0);
+        assert_eq!(header.face_count, 6);
+        assert_eq!(header.level_count, 2);
+        assert_levels_ordered(&file, 2, 8);
+
+        let (basic, samples) = descriptor(&reader);
+        assert_eq!(basic.transfer_function, Some(TransferFunction::Linear));
+        assert_eq!(basic.bytes_planes[0], 8);
+        assert_eq!(samples.len(), 4);
+        for (i, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.bit_offset, i as u32 * 16);
+            assert_eq!(sample.bit_length, 16);
+            assert_eq!(
+                sample.channel_type_qualifiers,
+                ChannelTypeQualifiers::FLOAT | ChannelTypeQualifiers::SIGNED
+            );
+            assert_eq!(
+                (sample.lower, sample.upper),
+                ((-1.0f32).to_bits(), 1.0f32.to_bits())
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_data_length() {
+        let texture = texture(TextureFormat::Rgba8Unorm, UVec2::splat(2), 2, 1);
+        assert!(texture.write(&[0; 19]).is_err());
+        assert!(texture.write(&[0; 20]).is_ok());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        let texture = Ktx2Texture {
+            zstd_level: Some(3),
+            ..texture(TextureFormat::Rgba8Unorm, UVec2::new(4, 2), 3, 2)
+        };
+        let layers = layers(&[32, 8, 4], 2);
+        let file = write_and_read(&texture, &layers);
+        let reader = Reader::new(&file).unwrap();
+        assert_eq!(
+            reader.header().supercompression_scheme,
+            Some(SupercompressionScheme::Zstandard)
+        );
+        assert!(reader.supercompression_global_data().is_empty());
+
+        for (level, level_data) in reader.levels().enumerate() {
+            let expected: Vec<u8> = layers
+                .iter()
+                .flat_map(|levels| levels[level].iter().copied())
+                .collect();
+            let decompressed = zstd::bulk::decompress(level_data, expected.len()).unwrap();
+            assert_eq!(decompressed, expected, "level {level}");
+        }
+    }
+}
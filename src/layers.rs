@@ -0,0 +1,101 @@
+use bevy::{prelude::*, render::view::Layer};
+
+/// Hands out render layers to render-to-texture tasks so that each task camera only
+/// sees the entities of its own scene.
+///
+/// Layer 0 is the default layer of every entity and camera and is never handed out.
+/// Layers used by the app itself can be excluded using [`RenderLayerAllocator::reserve`].
+#[derive(Clone, Debug, Reflect)]
+pub struct RenderLayerAllocator {
+    reserved: u32,
+    allocated: u32,
+}
+
+impl Default for RenderLayerAllocator {
+    fn default() -> Self {
+        Self {
+            reserved: 1,
+            allocated: 0,
+        }
+    }
+}
+
+impl RenderLayerAllocator {
+    /// Number of layers supported by `RenderLayers`.
+    pub const TOTAL_LAYERS: Layer = 32;
+
+    /// Never hand out the given layer, e.g., because the app uses it for its own entities.
+    pub fn reserve(&mut self, layer: Layer) {
+        assert!(
+            layer < Self::TOTAL_LAYERS,
+            "Layer {} is out of range",
+            layer
+        );
+        self.reserved |= 1 << layer;
+    }
+
+    /// Returns the lowest layer that is neither reserved nor allocated.
+    pub fn allocate(&mut self) -> Option<Layer> {
+        let free = !(self.reserved | self.allocated);
+        if free == 0 {
+            return None;
+        }
+        let layer = free.trailing_zeros() as Layer;
+        self.allocated |= 1 << layer;
+        Some(layer)
+    }
+
+    /// Returns a previously allocated layer to the pool.
+    pub fn free(&mut self, layer: Layer) {
+        if layer < Self::TOTAL_LAYERS {
+            self.allocated &= !(1 << layer);
+        }
+    }
+
+    /// Whether the layer is currently handed out to a task.
+    pub fn is_allocated(&self, layer: Layer) -> bool {
+        layer < Self::TOTAL_LAYERS && self.allocated & (1 << layer) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocate_until_exhausted() {
+        let mut layers = RenderLayerAllocator::default();
+        let allocated: Vec<_> = std::iter::from_fn(|| layers.allocate()).collect();
+        // layer 0 is never handed out
+        assert_eq!(
+            allocated,
+            (1..RenderLayerAllocator::TOTAL_LAYERS).collect::<Vec<_>>()
+        );
+        assert_eq!(layers.allocate(), None);
+    }
+
+    #[test]
+    fn reserved_layers_are_skipped() {
+        let mut layers = RenderLayerAllocator::default();
+        layers.reserve(1);
+        layers.reserve(3);
+        assert_eq!(layers.allocate(), Some(2));
+        assert_eq!(layers.allocate(), Some(4));
+        assert!(!layers.is_allocated(1));
+    }
+
+    #[test]
+    fn freed_layers_are_reused() {
+        let mut layers = RenderLayerAllocator::default();
+        while layers.allocate().is_some() {}
+        layers.free(7);
+        assert!(!layers.is_allocated(7));
+        assert_eq!(layers.allocate(), Some(7));
+        assert_eq!(layers.allocate(), None);
+
+        // freeing a layer that was never handed out changes nothing
+        layers.free(0);
+        layers.free(RenderLayerAllocator::TOTAL_LAYERS);
+        assert_eq!(layers.allocate(), None);
+    }
+}
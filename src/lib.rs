@@ -1,22 +1,65 @@
 #![allow(dead_code)]
 
 use bevy::prelude::*;
-pub use render::{create_render_texture, RenderToTextureTasks};
+#[cfg(feature = "atlas")]
+pub use component::RenderToTextureAtlas;
+pub use component::{
+    BasisCompressionFormat, BasisCompressionSettings, BcnFormat, RenderToTexture,
+    RenderToTextureBackground, RenderToTextureCamera, RenderToTextureColorSpace,
+    RenderToTextureCompressor, RenderToTextureContainer, RenderToTextureDepth,
+    RenderToTextureLayout, RenderToTextureMode, RenderToTextureOutput, RenderToTextureResult,
+    RenderToTextureSampler,
+};
+pub use error::RenderToTextureError;
+pub use events::{RenderToTextureFailed, RenderToTextureFinished};
+pub use render::{
+    create_render_texture, RenderToTextureTaskId, RenderToTextureTasks, TaskResource,
+};
+pub use resample::RenderToTextureFilter;
+mod component;
+mod error;
+mod events;
 mod gpu2cpu;
+mod layers;
 mod render;
+mod resample;
+mod texel;
+mod tiles;
 
+#[cfg(feature = "atlas")]
+mod atlas;
+#[cfg(feature = "compress-bcn")]
+mod bcn;
 #[cfg(feature = "compress")]
 mod compress;
-
+#[cfg(feature = "ktx2")]
+mod ktx2;
 
 pub struct RenderToTexturePlugin;
 
 impl Plugin for RenderToTexturePlugin {
     fn build(&self, app: &mut App) {
         app //.register_type::<RenderToTextureTasks>()
+            .register_type::<RenderToTexture>()
+            .register_type::<RenderToTextureResult>()
+            .register_type::<TaskResource>()
+            .add_event::<RenderToTextureFinished>()
+            .add_event::<RenderToTextureFailed>()
             .insert_resource(RenderToTextureTasks::default())
             .add_plugins(gpu2cpu::ImageExportPlugin::default())
+            .add_plugins(tiles::TileProjectionPlugin)
+            .add_systems(PreStartup, render::sync_msaa)
             .add_systems(Startup, render::setup_supported_formats)
-            .add_systems(PreUpdate, render::update_render_to_texture);
+            .add_systems(
+                PreUpdate,
+                (
+                    component::despawn_render_to_texture_tasks,
+                    render::update_render_to_texture,
+                    events::send_render_to_texture_events,
+                    component::update_render_to_texture_results,
+                    component::spawn_render_to_texture_tasks,
+                )
+                    .chain(),
+            );
     }
 }
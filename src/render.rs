@@ -1,16 +1,45 @@
-use crate::gpu2cpu::{ExtractableImages, ImageExportBundle, ImageExportSource};
+#[cfg(feature = "atlas")]
+use crate::{atlas, component::RenderToTextureAtlas};
+use crate::{
+    component::{
+        BasisCompressionFormat, RenderToTexture, RenderToTextureBackground, RenderToTextureCamera,
+        RenderToTextureCompressor, RenderToTextureContainer, RenderToTextureDepth,
+        RenderToTextureLayout, RenderToTextureMode, RenderToTextureOutput,
+    },
+    error::RenderToTextureError,
+    events::RenderToTextureFinished,
+    gpu2cpu::{
+        ExportId, ExtractableImages, ImageExportBundle, ImageExportSettings, ImageExportSource,
+        ViewTextureExport,
+    },
+    layers::RenderLayerAllocator,
+    resample, texel,
+    tiles::{TileGrid, TileProjection},
+};
+#[cfg(feature = "atlas")]
+use bevy::render::camera::Viewport;
 use bevy::{
+    core_pipeline::{
+        prepass::{MotionVectorPrepass, NormalPrepass},
+        tonemapping::{DebandDither, Tonemapping},
+    },
     prelude::*,
     render::{
+        camera::{CameraOutputMode, ScalingMode},
         render_asset::RenderAssetUsages,
         render_resource::{
-            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+            BlendState, Extent3d, LoadOp, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension,
+            WgpuFeatures,
         },
-        texture::{CompressedImageFormats, ImageSampler, ImageType},
+        settings::WgpuLimits,
+        texture::{CompressedImageFormats, ImageFormat, ImageSampler, ImageType},
         view::RenderLayers,
     },
     utils::HashMap,
 };
+use futures::channel::oneshot;
+use std::future::Future;
 
 #[derive(Default, Reflect, Clone, PartialEq)]
 pub enum RenderToTextureTaskStage {
@@ -18,136 +47,1010 @@ pub enum RenderToTextureTaskStage {
     Initialized,
     ReadyForRendering,
     RenderedResultCopiedBack,
+    /// The result is compressed or wrapped in its container in the background.
+    Compressing,
     ReadyForReading,
     ResultReceived,
     TaskDone,
+    Failed,
+    /// Renders a region of an atlas task, which produces the result.
+    #[cfg(feature = "atlas")]
+    AtlasRegion,
+}
+
+/// A lightweight handle to a task in [`RenderToTextureTasks`].
+///
+/// Like an `Entity`, the id is generational: once a task is removed, its id won't
+/// resolve to a task added later, even if the slot gets reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub struct RenderToTextureTaskId {
+    index: u32,
+    generation: u32,
+}
+
+impl From<RenderToTextureTaskId> for ExportId {
+    fn from(id: RenderToTextureTaskId) -> Self {
+        Self((id.index as u64) << 32 | id.generation as u64)
+    }
 }
 
-#[derive(Default, Reflect, Clone)]
+type ImageSender = oneshot::Sender<Result<Image, RenderToTextureError>>;
+
+#[derive(Default, Reflect)]
 pub struct RenderToTextureTask {
-    width: u32,
-    height: u32,
-    should_compress: bool,
-    target: Handle<Image>,
+    label: Option<String>,
+    settings: RenderToTexture,
+    /// One render target per layer of the result.
+    targets: Vec<Handle<Image>>,
     pub stage: RenderToTextureTaskStage,
-    camera: Option<Entity>,
+    /// One camera per layer of the result.
+    cameras: Vec<Entity>,
     layer: u8,
     is_srgb: bool,
     bundle: Option<Entity>,
     data: Vec<u8>,
-    allow_changes: bool,
+    /// The entity holding the `RenderToTexture` component this task was created for.
+    owner: Option<Entity>,
+    /// The image that was handed out with the `RenderToTextureFinished` event.
+    result: Option<Handle<Image>>,
+    #[reflect(ignore)]
+    error: Option<RenderToTextureError>,
+    notified: bool,
+    /// Futures waiting for the next result.
+    #[reflect(ignore)]
+    waiters: Vec<ImageSender>,
+    /// Whether the background pattern has to be written to the target again before rendering.
+    restore_background: bool,
+    /// Data of the outputs besides the color image.
+    #[reflect(ignore)]
+    extra_data: HashMap<RenderToTextureOutput, Vec<u8>>,
+    /// Images of the outputs besides the color image, handed out together with `result`.
+    extra_results: HashMap<RenderToTextureOutput, Handle<Image>>,
+    /// The regions of an atlas task, rendered by the cameras of the region tasks.
+    #[cfg(feature = "atlas")]
+    #[reflect(ignore)]
+    regions: Vec<AtlasRegion>,
+    /// The layout of an atlas, created together with each result.
+    #[cfg(feature = "atlas")]
+    atlas: Option<RenderToTextureAtlas>,
+    /// The tiles the result is rendered in, one after another.
+    tiles: TileGrid,
+    /// Data of the tiles rendered so far.
+    #[reflect(ignore)]
+    tile_data: Vec<Vec<u8>>,
+    /// Receives the result of the compression and the container running in the background.
+    #[reflect(ignore)]
+    compression: Option<oneshot::Receiver<Result<Vec<u8>, RenderToTextureError>>>,
 }
 
+/// A region of an atlas task and the camera of the region task rendering it.
+#[cfg(feature = "atlas")]
+#[derive(Clone)]
+struct AtlasRegion {
+    task: RenderToTextureTaskId,
+    camera: Entity,
+    size: UVec2,
+    rect: URect,
+}
+
+/// Turns the data of the color image into compressed data or a container.
+type Encoder = Box<dyn FnOnce(Vec<u8>) -> Result<Vec<u8>, RenderToTextureError> + Send>;
+
 impl RenderToTextureTask {
     pub fn new(
-        width: u32,
-        height: u32,
-        should_compress: bool,
+        label: Option<&str>,
+        settings: RenderToTexture,
         commands: &mut Commands,
         images: &mut ResMut<Assets<Image>>,
-        allow_changes: bool,
+        layer: u8,
+        limits: &WgpuLimits,
     ) -> Self {
-        // TODO: always 1 is not sufficient
-        let layer = 1;
-        let (target, camera_id) =
-            create_render_texture(width, height, commands, images, layer, false);
+        let format = settings.target_format();
+        let tiles = TileGrid::new(
+            settings.render_size(),
+            format.block_copy_size(None).unwrap_or(0),
+            limits.max_texture_dimension_2d,
+            limits.max_buffer_size,
+        );
+        if let Err(error) =
+            validate_settings(&settings).and_then(|()| validate_tiling(&settings, &tiles))
+        {
+            return Self {
+                layer,
+                ..Self::failed(label, settings, error)
+            };
+        }
+        #[cfg(feature = "atlas")]
+        if let RenderToTextureLayout::AtlasRegion(_) = settings.layout {
+            // the camera renders to the target of the atlas, see `RenderToTextureTasks::add_region`
+            return Self {
+                label: label.map(str::to_string),
+                settings,
+                layer,
+                stage: RenderToTextureTaskStage::AtlasRegion,
+                ..Default::default()
+            };
+        }
+
+        let hdr = needs_hdr(format);
+        let targets: Vec<_> = (0..settings.layout.layer_count())
+            .map(|_| {
+                // without tiling, the only tile covers the whole image
+                let mut target = create_render_target(
+                    tiles.tile_size,
+                    format,
+                    TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                );
+                fill_background(&mut target, &settings, UVec2::ZERO);
+                images.add(target)
+            })
+            .collect();
+
+        let mut cameras = Vec::new();
+        for (i, (kind, transform)) in settings.layer_cameras().into_iter().enumerate() {
+            let target = targets[i].clone();
+            if let RenderToTextureCamera::Existing(camera) = kind {
+                retarget_camera(camera, target, layer, hdr, transform, commands);
+                cameras.push(camera);
+                continue;
+            }
+
+            let camera = render_camera(target, hdr, &settings);
+            let tile = tiles.is_tiled().then(|| tiles.tile_rect(0));
+            cameras.push(spawn_render_camera(
+                camera, layer, &settings, kind, transform, tile, commands,
+            ));
+        }
 
         return Self {
-            width,
-            height,
+            label: label.map(str::to_string),
+            settings,
             layer,
-            target,
-            is_srgb: true, // TODO
-            should_compress,
-            allow_changes,
-            camera: Some(camera_id),
+            tiles,
+            targets,
+            is_srgb: format.is_srgb(),
+            cameras,
             stage: RenderToTextureTaskStage::Initialized,
             ..Default::default()
         };
     }
 
+    /// Creates a task that failed before it got a render layer or a camera.
+    /// Layer 0 is never handed out, so returning it to the allocator does nothing.
+    fn failed(label: Option<&str>, settings: RenderToTexture, error: RenderToTextureError) -> Self {
+        Self {
+            label: label.map(str::to_string),
+            settings,
+            stage: RenderToTextureTaskStage::Failed,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+
+    /// The settings the task was created with.
+    pub fn settings(&self) -> &RenderToTexture {
+        &self.settings
+    }
+
+    /// The camera rendering the task's layer. Gone once a `RenderToTextureMode::Once` task finished.
+    /// For cubemaps and texture arrays, this is the camera of the first layer.
+    pub fn camera(&self) -> Option<Entity> {
+        self.cameras.first().copied()
+    }
+
+    /// The cameras rendering each layer of the result, see [`RenderToTextureTask::camera`].
+    pub fn cameras(&self) -> &[Entity] {
+        &self.cameras
+    }
+
+    pub(crate) fn owner(&self) -> Option<Entity> {
+        self.owner
+    }
+
+    /// The debug label given when the task was added.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
     pub fn get_layer(&self) -> RenderLayers {
         RenderLayers::layer(self.layer)
     }
 
     pub fn size(&self) -> UVec2 {
-        UVec2::new(self.width, self.height)
+        self.settings.size
     }
 
     pub fn ready(&self) -> bool {
         self.stage == RenderToTextureTaskStage::ReadyForReading
     }
 
+    /// The image of the last render, available once the task is ready.
+    pub fn result(&self) -> Option<&Handle<Image>> {
+        self.result.as_ref()
+    }
+
+    /// The image of an output of the last render, available once the task is ready.
+    pub fn output(&self, output: RenderToTextureOutput) -> Option<&Handle<Image>> {
+        match output {
+            RenderToTextureOutput::Color => self.result.as_ref(),
+            _ => self.extra_results.get(&output),
+        }
+    }
+
+    /// The regions of the result of an atlas task, available once the task is ready.
+    #[cfg(feature = "atlas")]
+    pub fn atlas(&self) -> Option<&RenderToTextureAtlas> {
+        self.atlas.as_ref()
+    }
+
+    /// Why the task failed, if it did.
+    pub fn error(&self) -> Option<&RenderToTextureError> {
+        self.error.as_ref()
+    }
+
     pub fn free(&mut self, commands: &mut Commands) {
         assert!(
             self.stage == RenderToTextureTaskStage::TaskDone
+                || self.stage == RenderToTextureTaskStage::Compressing
                 || self.stage == RenderToTextureTaskStage::ReadyForReading
-                || self.stage == RenderToTextureTaskStage::ResultReceived,
+                || self.stage == RenderToTextureTaskStage::ResultReceived
+                || self.stage == RenderToTextureTaskStage::Failed,
             "Task not done"
         );
-        if let Some(c) = self.camera {
-            commands.entity(c).despawn_recursive();
-            self.camera = None;
+        self.release(commands);
+    }
+
+    /// Whether the task can be removed. `RenderToTextureMode::Once` tasks are finished in the frame
+    /// after their `RenderToTextureFinished` or `RenderToTextureFailed` event was sent, so the
+    /// result can still be read in the frame of the event.
+    fn is_finished(&self) -> bool {
+        match self.stage {
+            RenderToTextureTaskStage::TaskDone => true,
+            RenderToTextureTaskStage::ReadyForReading
+            | RenderToTextureTaskStage::ResultReceived
+            | RenderToTextureTaskStage::Failed => {
+                self.notified && self.settings.mode == RenderToTextureMode::Once
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the task spawned its camera itself.
+    pub(crate) fn owns_camera(&self) -> bool {
+        !matches!(self.settings.camera, RenderToTextureCamera::Existing(_))
+    }
+
+    fn release(&mut self, commands: &mut Commands) {
+        let mut cameras = std::mem::take(&mut self.cameras);
+        if !self.owns_camera() {
+            // leave cameras we don't own alive, but stop them from rendering to the target
+            for camera in cameras.drain(..) {
+                commands.add(move |world: &mut World| {
+                    let Some(mut entity) = world.get_entity_mut(camera) else {
+                        return;
+                    };
+                    entity.remove::<ViewTextureExport>();
+                    if let Some(mut camera) = entity.get_mut::<Camera>() {
+                        camera.is_active = false;
+                    }
+                });
+            }
+        }
+
+        // the entities might already be gone together with the owner
+        for entity in cameras.into_iter().chain(self.bundle.take()) {
+            if let Some(entity) = commands.get_entity(entity) {
+                entity.despawn_recursive();
+            }
         }
-        if let Some(b) = self.bundle {
-            commands.entity(b).despawn_recursive();
-            self.bundle = None;
+    }
+
+    fn set_camera_active(&self, cameras: &mut Query<&mut Camera>, active: bool) {
+        // an atlas renders with the cameras of its regions
+        #[cfg(feature = "atlas")]
+        let regions = self.regions.iter().map(|region| region.camera);
+        #[cfg(not(feature = "atlas"))]
+        let regions = std::iter::empty();
+        for camera in self.cameras.iter().copied().chain(regions) {
+            if let Ok(mut camera) = cameras.get_mut(camera) {
+                camera.is_active = active;
+            }
         }
     }
 
+    /// Renders the task again. Region tasks are rendered by their atlas instead.
     pub fn rerender(&mut self) {
+        // without a camera, there is nothing to render
+        #[cfg(feature = "atlas")]
+        let has_camera = !self.cameras.is_empty() || !self.regions.is_empty();
+        #[cfg(not(feature = "atlas"))]
+        let has_camera = !self.cameras.is_empty();
+        if !has_camera || self.is_region() {
+            return;
+        }
         self.stage = RenderToTextureTaskStage::ReadyForRendering;
+        self.restore_background = self.settings.background.is_pattern();
+        self.result = None;
+        self.extra_results.clear();
+        self.tile_data.clear();
+        // drop the compression of the previous result once it finishes
+        self.compression = None;
+        self.error = None;
+        self.notified = false;
+        #[cfg(feature = "atlas")]
+        {
+            self.atlas = None;
+        }
+    }
+
+    /// Whether the task renders a region of an atlas task.
+    fn is_region(&self) -> bool {
+        #[cfg(feature = "atlas")]
+        if let RenderToTextureLayout::AtlasRegion(_) = self.settings.layout {
+            return true;
+        }
+        false
+    }
+
+    /// Whether the task is an atlas that has no regions yet.
+    fn is_empty_atlas(&self) -> bool {
+        #[cfg(feature = "atlas")]
+        if let RenderToTextureLayout::Atlas { .. } = self.settings.layout {
+            return self.regions.is_empty();
+        }
+        false
+    }
+
+    /// Updates the viewports of the region cameras after the regions changed.
+    #[cfg(feature = "atlas")]
+    fn arrange_regions(&self, commands: &mut Commands) {
+        let count = self.regions.len();
+        let factor = self.settings.supersampling.max(1);
+        // the clear color and output mode a camera rendering the whole target would use
+        let first = render_camera(Handle::default(), false, &self.settings);
+        for (i, region) in self.regions.iter().enumerate() {
+            let viewport = Viewport {
+                physical_position: region.rect.min * factor,
+                physical_size: region.rect.size() * factor,
+                ..default()
+            };
+            // The cameras share the main texture, which is only cleared by the first one.
+            // Only the last one writes it to the target, so patterns are blended over once.
+            let order = i as isize - count as isize;
+            let clear_color = if i == 0 {
+                first.clear_color.clone()
+            } else {
+                ClearColorConfig::None
+            };
+            let output_mode = if i + 1 < count {
+                CameraOutputMode::Skip
+            } else {
+                first.output_mode
+            };
+            let entity = region.camera;
+            commands.add(move |world: &mut World| {
+                if let Some(mut camera) = world.get_mut::<Camera>(entity) {
+                    camera.viewport = Some(viewport);
+                    camera.order = order;
+                    camera.clear_color = clear_color;
+                    camera.output_mode = output_mode;
+                }
+            });
+        }
+    }
+
+    /// Takes the compressed data once the background compression finished.
+    fn poll_compression(&mut self) {
+        let Some(compression) = self.compression.as_mut() else {
+            return;
+        };
+        match compression.try_recv() {
+            Ok(None) => return,
+            Ok(Some(Ok(data))) => {
+                self.data = data;
+                self.stage = RenderToTextureTaskStage::ReadyForReading;
+            }
+            Ok(Some(Err(e))) => {
+                self.error = Some(e);
+                self.stage = RenderToTextureTaskStage::Failed;
+            }
+            Err(oneshot::Canceled) => {
+                // the sender is dropped when the compressor panics
+                self.error = Some(RenderToTextureError::InvalidImage(
+                    "Compression failed".to_string(),
+                ));
+                self.stage = RenderToTextureTaskStage::Failed;
+            }
+        }
+        self.compression = None;
+    }
+
+    /// The pixels of the tile that is rendered next.
+    fn current_tile(&self) -> URect {
+        self.tiles.tile_rect(self.tile_data.len())
+    }
+
+    /// Stores the data of the tile that was just rendered.
+    /// Returns whether all tiles have been rendered.
+    fn collect_tile(&mut self) -> bool {
+        if !self.tiles.is_tiled() {
+            return true;
+        }
+        self.tile_data.push(std::mem::take(&mut self.data));
+        self.tile_data.len() == self.tiles.len()
+    }
+
+    /// Post-processes the data read back from the GPU.
+    ///
+    /// `projection` is the projection matrix of the camera, used to linearize depth.
+    fn process_data(&mut self, projection: Mat4) -> Result<(), RenderToTextureError> {
+        for (output, data) in self.extra_data.iter_mut() {
+            *data = process_output(*output, data, &self.settings, projection)?;
+        }
+
+        let format = self.settings.target_format();
+        let render_size = self.settings.render_size();
+        if self.tiles.is_tiled() {
+            let texel_size = format.block_copy_size(None).unwrap_or(0) as usize;
+            let tiles = std::mem::take(&mut self.tile_data);
+            self.data = self
+                .tiles
+                .stitch(&tiles, render_size, texel_size)
+                .ok_or_else(|| {
+                    RenderToTextureError::InvalidImage("A tile wasn't read back".to_string())
+                })?;
+        }
+        if self.settings.layout == RenderToTextureLayout::Cube {
+            mirror_horizontally(&mut self.data, render_size.x, format);
+        }
+
+        if self.settings.supersampling > 1 {
+            let layer_size = self.data.len() / self.targets.len().max(1);
+            let mut data = Vec::new();
+            for layer in self.data.chunks(layer_size.max(1)) {
+                data.extend(
+                    resample::resize_data(
+                        layer,
+                        format,
+                        render_size,
+                        self.settings.size,
+                        self.settings.downsample_filter,
+                    )
+                    .ok_or(RenderToTextureError::UnsupportedFormat(format))?,
+                );
+            }
+            self.data = data;
+        }
+
+        let levels = self.settings.mip_level_count();
+        if levels > 1 {
+            let layers = self.targets.len() as u32;
+            self.data =
+                resample::layered_mip_chain(&self.data, format, self.settings.size, levels, layers)
+                    .ok_or(RenderToTextureError::UnsupportedFormat(format))?;
+        }
+
+        if !self.settings.compress && self.settings.container == RenderToTextureContainer::Raw {
+            return Ok(());
+        }
+
+        let compress = self.compressor()?;
+        let wrap = self.container()?;
+        // compressing large textures takes seconds, so don't block the frame
+        let data = std::mem::take(&mut self.data);
+        let (sender, receiver) = oneshot::channel();
+        bevy::tasks::AsyncComputeTaskPool::get()
+            .spawn(async move {
+                let _ = sender.send(compress(data).and_then(wrap));
+            })
+            .detach();
+        self.compression = Some(receiver);
+        Ok(())
+    }
+
+    /// Compresses the data of the color image with the task's compressor, if any.
+    fn compressor(&self) -> Result<Encoder, RenderToTextureError> {
+        if !self.settings.compress {
+            return Ok(Box::new(Ok));
+        }
+        // only if the feature of the backend is enabled
+        match self.settings.compressor {
+            #[cfg(feature = "compress")]
+            RenderToTextureCompressor::Basis => {
+                let (size, is_srgb, basis) = (self.size(), self.is_srgb, self.settings.basis);
+                Ok(Box::new(move |data| {
+                    Ok(crate::compress::compress_to_basis_raw(
+                        &data, size, is_srgb, &basis,
+                    ))
+                }))
+            }
+            #[cfg(feature = "compress-bcn")]
+            RenderToTextureCompressor::Bcn(format) => {
+                let size = self.settings.size;
+                let levels = self.settings.mip_level_count();
+                let layers = self.settings.layout.layer_count();
+                Ok(Box::new(move |data| {
+                    Ok(crate::bcn::compress(&data, format, size, levels, layers))
+                }))
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(RenderToTextureError::CompressionUnavailable),
+        }
+    }
+
+    /// Wraps the (compressed) data of the color image in the task's container.
+    fn container(&self) -> Result<Encoder, RenderToTextureError> {
+        match self.settings.container {
+            RenderToTextureContainer::Raw => Ok(Box::new(Ok)),
+            #[cfg(feature = "ktx2")]
+            RenderToTextureContainer::Ktx2 { zstd_level } => {
+                let format = match self.settings.compressor {
+                    RenderToTextureCompressor::Basis if self.settings.compress => {
+                        crate::ktx2::Ktx2Format::Basis
+                    }
+                    RenderToTextureCompressor::Bcn(format) if self.settings.compress => {
+                        crate::ktx2::Ktx2Format::Texture(format.texture_format(self.is_srgb))
+                    }
+                    _ => {
+                        let format = self.settings.target_format();
+                        if !crate::ktx2::is_supported(format) {
+                            return Err(RenderToTextureError::UnsupportedFormat(format));
+                        }
+                        crate::ktx2::Ktx2Format::Texture(format)
+                    }
+                };
+                if zstd_level.is_some() && !cfg!(feature = "zstd") {
+                    return Err(RenderToTextureError::ContainerUnavailable);
+                }
+                let texture = crate::ktx2::Ktx2Texture {
+                    format,
+                    size: self.settings.size,
+                    levels: self.settings.mip_level_count(),
+                    layers: self.settings.layout.layer_count(),
+                    cube: self.settings.layout == RenderToTextureLayout::Cube,
+                    srgb: self.is_srgb,
+                    zstd_level,
+                };
+                Ok(Box::new(move |data| texture.write(&data)))
+            }
+            #[cfg(not(feature = "ktx2"))]
+            RenderToTextureContainer::Ktx2 { .. } => {
+                Err(RenderToTextureError::ContainerUnavailable)
+            }
+        }
+    }
+
+    /// Creates the images of the outputs besides the color image.
+    fn create_extra_images(&self) -> Vec<(RenderToTextureOutput, Image)> {
+        self.extra_data
+            .iter()
+            .map(|(output, data)| {
+                let format = output.format().unwrap_or(self.settings.target_format());
+                let mut image = Image::new(
+                    Extent3d {
+                        width: self.settings.size.x,
+                        height: self.settings.size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    TextureDimension::D2,
+                    data.clone(),
+                    format,
+                    RenderAssetUsages::default(),
+                );
+                image.sampler = self.settings.sampler.image_sampler(false);
+                (*output, image)
+            })
+            .collect()
+    }
+
+    /// Loads the data as an image file. Basis and UASTC data is transcoded to the best format
+    /// the device supports, i.e., ASTC, BC7 or ETC2. If that fails, it is transcoded to
+    /// uncompressed RGBA instead.
+    fn load_image(
+        &self,
+        file_format: ImageFormat,
+        supported_compressed_formats: CompressedImageFormats,
+    ) -> Result<Image, RenderToTextureError> {
+        let load = |formats| {
+            Image::from_buffer(
+                &self.data,
+                ImageType::Format(file_format),
+                formats,
+                self.is_srgb,
+                ImageSampler::Default,
+                RenderAssetUsages::default(),
+            )
+        };
+        load(supported_compressed_formats)
+            .or_else(|e| {
+                if supported_compressed_formats == CompressedImageFormats::NONE {
+                    return Err(e);
+                }
+                warn!(
+                    "Failed to transcode the result of a render to texture task, falling back to RGBA: {}",
+                    e
+                );
+                load(CompressedImageFormats::NONE)
+            })
+            .map_err(|e| RenderToTextureError::InvalidImage(e.to_string()))
+    }
+
+    fn create_image(
+        &self,
+        supported_compressed_formats: CompressedImageFormats,
+    ) -> Result<Image, RenderToTextureError> {
+        // load the container like it would be loaded from disk
+        let file_format = if self.settings.container != RenderToTextureContainer::Raw {
+            Some(ImageFormat::Ktx2)
+        } else if self.settings.compresses_to_basis() {
+            Some(ImageFormat::Basis)
+        } else {
+            None
+        };
+
+        if let Some(file_format) = file_format {
+            let mut image = self.load_image(file_format, supported_compressed_formats)?;
+            image.sampler = self
+                .settings
+                .sampler
+                .image_sampler(image.texture_descriptor.mip_level_count > 1);
+            Ok(image)
+        } else {
+            // `Image::new` doesn't accept data with mip levels
+            let mut image = Image {
+                data: self.data.clone(),
+                ..default()
+            };
+            let descriptor = &mut image.texture_descriptor;
+            descriptor.size = Extent3d {
+                width: self.settings.size.x,
+                height: self.settings.size.y,
+                depth_or_array_layers: self.settings.layout.layer_count(),
+            };
+            descriptor.format = match self.settings.compressor {
+                RenderToTextureCompressor::Bcn(format) if self.settings.compress => {
+                    if !supported_compressed_formats.contains(CompressedImageFormats::BC) {
+                        return Err(RenderToTextureError::UnsupportedFormat(
+                            format.texture_format(self.is_srgb),
+                        ));
+                    }
+                    format.texture_format(self.is_srgb)
+                }
+                _ => self.settings.target_format(),
+            };
+            descriptor.mip_level_count = self.settings.mip_level_count();
+            image.sampler = self
+                .settings
+                .sampler
+                .image_sampler(descriptor.mip_level_count > 1);
+            let dimension = match self.settings.layout {
+                RenderToTextureLayout::Single => None,
+                #[cfg(feature = "atlas")]
+                RenderToTextureLayout::Atlas { .. } | RenderToTextureLayout::AtlasRegion(_) => None,
+                RenderToTextureLayout::Cube => Some(TextureViewDimension::Cube),
+                RenderToTextureLayout::Array(_) => Some(TextureViewDimension::D2Array),
+            };
+            if dimension.is_some() {
+                image.texture_view_descriptor = Some(TextureViewDescriptor {
+                    dimension,
+                    ..default()
+                });
+            }
+            Ok(image)
+        }
     }
 }
 
-#[derive(Default, Resource, Clone)]
+/// The render to texture tasks of the app.
+///
+/// Not `Clone`, because the tasks hold the senders of the futures waiting for their results.
+#[derive(Default, Resource)]
 pub struct RenderToTextureTasks {
-    tasks: HashMap<String, RenderToTextureTask>,
-    // TODO: populate this!
+    tasks: HashMap<RenderToTextureTaskId, RenderToTextureTask>,
+    generations: Vec<u32>,
+    free_indices: Vec<u32>,
+    layers: RenderLayerAllocator,
+    /// Limits of the render device, larger tasks are rendered in tiles.
+    limits: WgpuLimits,
+    /// The compressed formats of the render device, set by `setup_supported_formats`.
     supported_compressed_formats: CompressedImageFormats,
+    /// The app's `Msaa` resource, set by `sync_msaa` and `update_render_to_texture`.
+    msaa: Msaa,
 }
 
-#[derive(Default, Component, Clone, Reflect)]
-pub struct TaskResource(pub String);
+/// Links an entity to its render to texture task.
+#[derive(Component, Clone, Reflect)]
+pub struct TaskResource(pub RenderToTextureTaskId);
 
 impl RenderToTextureTasks {
-    /// should_compress: whether to use universal basis compression. This will also generate mipmaps.
+    /// `label`: an optional name that is only used for debugging.
+    ///
+    /// Once all render layers are in use, the task fails with `RenderToTextureError::NoFreeLayer`.
+    /// Tasks with extra outputs like the depth buffer fail with `RenderToTextureError::MultisampledOutput`
+    /// unless the app inserted `Msaa::Off`.
     pub fn add(
         &mut self,
-        name: String,
-        width: u32,
-        height: u32,
-        should_compress: bool,
+        label: Option<&str>,
+        settings: RenderToTexture,
         commands: &mut Commands,
         images: &mut ResMut<Assets<Image>>,
-        allow_changes: bool,
-    ) {
-        let task = RenderToTextureTask::new(
-            width,
-            height,
-            should_compress,
+    ) -> RenderToTextureTaskId {
+        let layer = self
+            .validate_msaa(&settings)
+            .and_then(|()| self.allocate_layer(&settings));
+        let layer = match layer {
+            Ok(layer) => layer,
+            Err(error) => {
+                // reported with a `RenderToTextureFailed` event like any other failure
+                let id = self.allocate_id();
+                self.tasks
+                    .insert(id, RenderToTextureTask::failed(label, settings, error));
+                return id;
+            }
+        };
+        #[allow(unused_mut)]
+        let mut task =
+            RenderToTextureTask::new(label, settings, commands, images, layer, &self.limits);
+        let id = self.allocate_id();
+        #[cfg(feature = "atlas")]
+        if let RenderToTextureLayout::AtlasRegion(atlas) = task.settings.layout {
+            if task.stage == RenderToTextureTaskStage::AtlasRegion {
+                if let Err(error) = self.add_region(atlas, id, &mut task, commands) {
+                    task.error = Some(error);
+                    task.stage = RenderToTextureTaskStage::Failed;
+                }
+            }
+        }
+        self.tasks.insert(id, task);
+        id
+    }
+
+    /// Region tasks render the layer of their atlas, all other tasks get a layer of their own.
+    #[cfg_attr(not(feature = "atlas"), allow(unused_variables))]
+    fn allocate_layer(&mut self, settings: &RenderToTexture) -> Result<u8, RenderToTextureError> {
+        #[cfg(feature = "atlas")]
+        if let RenderToTextureLayout::AtlasRegion(atlas) = settings.layout {
+            // `add_region` fails if there is no such atlas
+            return Ok(self.tasks.get(&atlas).map_or(0, |atlas| atlas.layer));
+        }
+        self.layers
+            .allocate()
+            .ok_or(RenderToTextureError::NoFreeLayer)
+    }
+
+    /// Multisampled depth and prepass textures can't be copied, so extra outputs need `Msaa::Off`.
+    fn validate_msaa(&self, settings: &RenderToTexture) -> Result<(), RenderToTextureError> {
+        match settings.extra_outputs().first() {
+            Some(output) if self.msaa.samples() > 1 => {
+                Err(RenderToTextureError::MultisampledOutput(*output))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Spawns the camera of a region task and packs the regions of its atlas again.
+    #[cfg(feature = "atlas")]
+    fn add_region(
+        &mut self,
+        atlas_id: RenderToTextureTaskId,
+        id: RenderToTextureTaskId,
+        region: &mut RenderToTextureTask,
+        commands: &mut Commands,
+    ) -> Result<(), RenderToTextureError> {
+        let invalid = |reason: &str| Err(RenderToTextureError::InvalidSettings(reason.to_string()));
+        let Some(atlas) = self.tasks.get_mut(&atlas_id) else {
+            return invalid("the atlas of the region doesn't exist");
+        };
+        let RenderToTextureLayout::Atlas { padding } = atlas.settings.layout else {
+            return invalid("the atlas of the region is not an atlas task");
+        };
+        // a `Once` atlas releases its target as soon as it rendered
+        let rendered = !matches!(
+            atlas.stage,
+            RenderToTextureTaskStage::Initialized | RenderToTextureTaskStage::ReadyForRendering
+        );
+        let finished = matches!(
+            atlas.stage,
+            RenderToTextureTaskStage::Failed | RenderToTextureTaskStage::TaskDone
+        );
+        if finished || (rendered && atlas.settings.mode == RenderToTextureMode::Once) {
+            return invalid("the atlas of the region already finished");
+        }
+
+        let sizes: Vec<_> = atlas
+            .regions
+            .iter()
+            .map(|region| region.size)
+            .chain([region.settings.size])
+            .collect();
+        let Some(rects) = atlas::pack(&sizes, padding, atlas.settings.size) else {
+            return invalid("the region doesn't fit into the atlas");
+        };
+
+        let target = atlas.targets[0].clone();
+        let hdr = needs_hdr(atlas.settings.target_format());
+        let camera = spawn_render_camera(
+            render_camera(target, hdr, &atlas.settings),
+            region.layer,
+            &atlas.settings,
+            region.settings.camera,
+            region.settings.transform,
+            None,
             commands,
-            images,
-            allow_changes,
         );
+        region.cameras.push(camera);
+        atlas.regions.push(AtlasRegion {
+            task: id,
+            camera,
+            size: region.settings.size,
+            rect: URect::default(),
+        });
+        for (region, rect) in atlas.regions.iter_mut().zip(rects) {
+            region.rect = rect;
+        }
+        atlas.arrange_regions(commands);
+        if rendered {
+            atlas.rerender();
+        }
+        Ok(())
+    }
+
+    /// Removes a region task from its atlas, or the region tasks of an atlas task.
+    #[cfg(feature = "atlas")]
+    fn remove_regions(
+        &mut self,
+        id: RenderToTextureTaskId,
+        task: &RenderToTextureTask,
+        commands: &mut Commands,
+    ) {
+        match task.settings.layout {
+            RenderToTextureLayout::AtlasRegion(atlas) => {
+                let Some(atlas) = self.tasks.get_mut(&atlas) else {
+                    return;
+                };
+                let count = atlas.regions.len();
+                atlas.regions.retain(|region| region.task != id);
+                // the remaining regions keep their place
+                if atlas.regions.len() != count {
+                    atlas.arrange_regions(commands);
+                }
+            }
+            RenderToTextureLayout::Atlas { .. } => {
+                for region in &task.regions {
+                    self.remove(region.task, commands);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Creates the layouts of the atlas tasks that became ready.
+    #[cfg(feature = "atlas")]
+    pub(crate) fn create_atlas_layouts(&mut self, layouts: &mut Assets<TextureAtlasLayout>) {
+        for task in self.tasks.values_mut() {
+            let RenderToTextureLayout::Atlas { .. } = task.settings.layout else {
+                continue;
+            };
+            if task.notified
+                || task.atlas.is_some()
+                || task.stage != RenderToTextureTaskStage::ReadyForReading
+            {
+                continue;
+            }
+            let mut layout = TextureAtlasLayout::new_empty(task.settings.size.as_vec2());
+            let regions = task
+                .regions
+                .iter()
+                .map(|region| (region.task, layout.add_texture(region.rect.as_rect())))
+                .collect();
+            task.atlas = Some(RenderToTextureAtlas {
+                layout: layouts.add(layout),
+                regions,
+            });
+        }
+    }
+
+    /// Adds a task and returns a future that resolves to the rendered image.
+    ///
+    /// The future is `Send` and can be awaited in a task pool, e.g.,
+    /// `AsyncComputeTaskPool::get().spawn(async move { let image = future.await?; ... })`.
+    /// With `RenderToTextureMode::Once`, the task is finished as soon as the image was delivered.
+    pub fn request(
+        &mut self,
+        label: Option<&str>,
+        settings: RenderToTexture,
+        commands: &mut Commands,
+        images: &mut ResMut<Assets<Image>>,
+    ) -> impl Future<Output = Result<Image, RenderToTextureError>> {
+        let id = self.add(label, settings, commands, images);
+        self.wait(id)
+    }
+
+    /// Returns a future that resolves to the next image the task produces.
+    pub fn wait(
+        &mut self,
+        id: RenderToTextureTaskId,
+    ) -> impl Future<Output = Result<Image, RenderToTextureError>> {
+        let (sender, receiver) = oneshot::channel();
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.waiters.push(sender);
+        }
+        // the sender is dropped when the task is removed before producing an image
+        async move {
+            receiver
+                .await
+                .unwrap_or(Err(RenderToTextureError::Cancelled))
+        }
+    }
+
+    fn allocate_id(&mut self) -> RenderToTextureTaskId {
+        let index = self.free_indices.pop().unwrap_or_else(|| {
+            self.generations.push(0);
+            (self.generations.len() - 1) as u32
+        });
+        RenderToTextureTaskId {
+            index,
+            generation: self.generations[index as usize],
+        }
+    }
+
+    fn remove(&mut self, id: RenderToTextureTaskId, commands: &mut Commands) {
+        if let Some(mut task) = self.tasks.remove(&id) {
+            task.release(commands);
+            if !task.is_region() {
+                self.layers.free(task.layer);
+            }
+            let generation = &mut self.generations[id.index as usize];
+            *generation = generation.wrapping_add(1);
+            self.free_indices.push(id.index);
+            #[cfg(feature = "atlas")]
+            self.remove_regions(id, &task, commands);
+        }
+    }
+
+    /// Never assign the given render layer to a task. Use this for layers the app renders on itself.
+    pub fn reserve_layer(&mut self, layer: u8) {
         assert!(
-            self.tasks.get(&name).is_none(),
-            "Task with name {} already exists",
-            name
+            !self.layers.is_allocated(layer),
+            "Layer {} is already in use by a render to texture task",
+            layer
         );
-        self.tasks.insert(name, task);
+        self.layers.reserve(layer);
+    }
+
+    /// Removes the task, despawns its entities and returns its render layer to the pool.
+    pub fn free(&mut self, id: RenderToTextureTaskId, commands: &mut Commands) {
+        self.remove(id, commands);
+    }
+
+    pub fn get(&self, id: RenderToTextureTaskId) -> Option<&RenderToTextureTask> {
+        self.tasks.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: RenderToTextureTaskId) -> Option<&mut RenderToTextureTask> {
+        self.tasks.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (RenderToTextureTaskId, &RenderToTextureTask)> {
+        self.tasks.iter().map(|(id, task)| (*id, task))
     }
 
-    pub fn get(&self, name: &str) -> Option<&RenderToTextureTask> {
-        self.tasks.get(name)
+    pub(crate) fn set_owner(&mut self, id: RenderToTextureTaskId, owner: Entity) {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.owner = Some(owner);
+        }
     }
 
-    pub fn get_mut(&mut self, name: &str) -> Option<&mut RenderToTextureTask> {
-        self.tasks.get_mut(name)
+    pub(crate) fn find_owned_by(&self, owner: Entity) -> Option<RenderToTextureTaskId> {
+        self.iter()
+            .find(|(_, task)| task.owner == Some(owner))
+            .map(|(id, _)| id)
     }
 
-    pub fn read(&mut self, name: &str) -> Option<Vec<u8>> {
-        if let Some(task) = self.tasks.get_mut(name) {
+    /// Takes the data of a finished task and marks it as done. The data is laid out as described
+    /// by the task's `RenderToTextureContainer`, e.g., a KTX2 file that can be written to disk.
+    pub fn read(&mut self, id: RenderToTextureTaskId) -> Option<Vec<u8>> {
+        if let Some(task) = self.tasks.get_mut(&id) {
             if task.stage != RenderToTextureTaskStage::ReadyForReading {
                 return None;
             }
@@ -157,151 +1060,278 @@ impl RenderToTextureTasks {
         return None;
     }
 
-    pub fn image(&mut self, name: &str, finish: bool) -> Option<Image> {
-        // TODO: Delete the image when not in use anymore
+    /// Creates an image from the result of a finished task.
+    /// With `finish`, the task is marked as done and removed in the next frame.
+    pub fn image(&mut self, id: RenderToTextureTaskId, finish: bool) -> Option<Image> {
+        let image = self
+            .get(id)
+            .filter(|task| task.ready())?
+            .create_image(self.supported_compressed_formats)
+            .ok()?;
+        self.mark_received(id, finish);
+        Some(image)
+    }
 
-        if let Some(task) = self.tasks.get_mut(name) {
-            if task.stage != RenderToTextureTaskStage::ReadyForReading {
-                return None;
+    /// The image of a finished task, i.e., the one handed out with the `RenderToTextureFinished` event.
+    /// With `finish`, the task is marked as done and removed in the next frame.
+    pub fn image_handle(
+        &mut self,
+        id: RenderToTextureTaskId,
+        finish: bool,
+    ) -> Option<Handle<Image>> {
+        let image = self.get(id).filter(|task| task.ready())?.result.clone()?;
+        self.mark_received(id, finish);
+        Some(image)
+    }
+
+    /// The compressed formats the render device supports. Compressed results are transcoded
+    /// to one of them, or to uncompressed RGBA if there is none.
+    pub fn supported_compressed_formats(&self) -> CompressedImageFormats {
+        self.supported_compressed_formats
+    }
+
+    /// Marks the result of the task as received. Finished tasks are removed in the next frame.
+    pub(crate) fn mark_received(&mut self, id: RenderToTextureTaskId, finish: bool) {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            if task.stage == RenderToTextureTaskStage::ReadyForReading {
+                task.stage = if finish {
+                    RenderToTextureTaskStage::TaskDone
+                } else {
+                    RenderToTextureTaskStage::ResultReceived
+                };
             }
-            task.stage = RenderToTextureTaskStage::ResultReceived;
-            if finish {
-                task.stage = RenderToTextureTaskStage::TaskDone;
-            }
-            if task.should_compress {
-                return Some(
-                    Image::from_buffer(
-                        &task.data,
-                        ImageType::Format(bevy::render::texture::ImageFormat::Basis),
-                        self.supported_compressed_formats,
-                        true,
-                        ImageSampler::linear(), // TODO: mipmap trilinear?
-                        RenderAssetUsages::default(),
-                    )
-                    .unwrap(),
-                );
-            } else {
-                return Some(Image::new_fill(
-                    Extent3d {
-                        width: task.width,
-                        height: task.height,
-                        depth_or_array_layers: 1,
-                    },
-                    TextureDimension::D2,
-                    &task.data,
-                    TextureFormat::Rgba8UnormSrgb,
-                    RenderAssetUsages::default(),
-                ));
+        }
+    }
+
+    /// Creates the images of tasks that became ready and collects the tasks that failed since the last call.
+    pub(crate) fn collect_results(
+        &mut self,
+        images: &mut Assets<Image>,
+    ) -> Vec<Result<RenderToTextureFinished, (RenderToTextureTaskId, RenderToTextureError)>> {
+        let mut results = Vec::new();
+        for (id, task) in self.tasks.iter_mut() {
+            if task.notified {
+                continue;
             }
+            if task.stage == RenderToTextureTaskStage::ReadyForReading {
+                match task.create_image(self.supported_compressed_formats) {
+                    Ok(image) => {
+                        for waiter in task.waiters.drain(..) {
+                            let _ = waiter.send(Ok(image.clone()));
+                        }
+                        task.result = Some(images.add(image));
+                        task.extra_results = task
+                            .create_extra_images()
+                            .into_iter()
+                            .map(|(output, image)| (output, images.add(image)))
+                            .collect();
+                    }
+                    Err(e) => {
+                        task.error = Some(e);
+                        task.stage = RenderToTextureTaskStage::Failed;
+                    }
+                }
+            }
+            match task.stage {
+                RenderToTextureTaskStage::ReadyForReading if task.result.is_some() => {
+                    results.push(Ok(RenderToTextureFinished {
+                        task: *id,
+                        image: task.result.clone().unwrap(),
+                        outputs: task.extra_results.clone(),
+                        #[cfg(feature = "atlas")]
+                        atlas: task.atlas.clone(),
+                    }));
+                }
+                RenderToTextureTaskStage::Failed => {
+                    let error = task.error.clone().unwrap();
+                    for waiter in task.waiters.drain(..) {
+                        let _ = waiter.send(Err(error.clone()));
+                    }
+                    results.push(Err((*id, error)));
+                }
+                _ => continue,
+            }
+            task.notified = true;
         }
-        return None;
+        results
     }
 }
 
+/// Queries the compressed formats and limits of the render device.
+/// Without a render device, results are transcoded to uncompressed RGBA.
 pub fn setup_supported_formats(
-    device: Res<bevy::render::renderer::RenderDevice>,
+    device: Option<Res<bevy::render::renderer::RenderDevice>>,
     mut tasks: ResMut<RenderToTextureTasks>,
 ) {
+    let Some(device) = device else {
+        warn!("No render device found, compressed results are transcoded to RGBA");
+        return;
+    };
     tasks.supported_compressed_formats = CompressedImageFormats::from_features(device.features());
+    tasks.limits = device.limits();
+}
+
+/// Copies the `Msaa` resource before the tasks of startup systems are added.
+pub fn sync_msaa(msaa: Res<Msaa>, mut tasks: ResMut<RenderToTextureTasks>) {
+    tasks.msaa = *msaa;
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_render_to_texture(
     mut tasks: ResMut<RenderToTextureTasks>,
     mut cameras: Query<&mut Camera>,
+    mut tile_projections: Query<&mut TileProjection>,
     mut commands: Commands,
     mut image_exports: ResMut<Assets<ImageExportSource>>,
     mut extractable_images: ResMut<ExtractableImages>,
+    mut images: ResMut<Assets<Image>>,
+    msaa: Res<Msaa>,
     // mut settings: Query<&mut ImageExportSettings>,
 ) {
+    let tasks = tasks.as_mut();
+    tasks.msaa = *msaa;
+
     // remove finished tasks
-    tasks
+    let finished: Vec<_> = tasks
         .tasks
-        .retain(|_, task| task.stage != RenderToTextureTaskStage::TaskDone);
+        .iter()
+        .filter(|(_, task)| task.is_finished())
+        .map(|(id, _)| *id)
+        .collect();
+    for id in finished {
+        tasks.remove(id, &mut commands);
+    }
 
-    if extractable_images.raw.len() > 0 {
-        for (_, task) in tasks.tasks.iter_mut() {
-            if task.stage == RenderToTextureTaskStage::ReadyForRendering {
-                task.data = extractable_images.raw.clone(); // TODO: avoid cloning
+    for (id, task) in tasks.tasks.iter_mut() {
+        if task.stage != RenderToTextureTaskStage::ReadyForRendering {
+            continue;
+        }
+        // all outputs are read back in the same frame
+        let outputs = task.settings.extra_outputs();
+        let raw = &mut extractable_images.raw;
+        let export = ExportId::from(*id);
+        if !raw.contains_key(&(export, RenderToTextureOutput::Color))
+            || !outputs
+                .iter()
+                .all(|output| raw.contains_key(&(export, *output)))
+        {
+            continue;
+        }
+        //println!("Image data received");
+        task.data = raw.remove(&(export, RenderToTextureOutput::Color)).unwrap();
+        task.extra_data = outputs
+            .into_iter()
+            .map(|output| (output, raw.remove(&(export, output)).unwrap()))
+            .collect();
+        task.stage = RenderToTextureTaskStage::RenderedResultCopiedBack;
+    }
 
-                //println!("Image data received");
+    // drop data that arrived for tasks which are not waiting for it anymore
+    extractable_images.raw.clear();
+    extractable_images.refresh.clear();
 
-                extractable_images.raw.clear();
-                task.stage = RenderToTextureTaskStage::RenderedResultCopiedBack;
-            }
+    for (id, task) in tasks.tasks.iter_mut() {
+        if task.stage == RenderToTextureTaskStage::RenderedResultCopiedBack && !task.collect_tile()
+        {
+            // render the next tile
+            task.stage = RenderToTextureTaskStage::ReadyForRendering;
+            task.restore_background = task.settings.background.is_pattern();
         }
 
-        // TODO: For some reason, the renderer always sends the image data twice... so we have to clear it here
-        // clear anyway
-        extractable_images.raw.clear();
-    }
-
-    let mut started_rendering = false;
-    for (_, task) in tasks.tasks.iter_mut() {
         match task.stage {
-            RenderToTextureTaskStage::ReadyForRendering => {}
             RenderToTextureTaskStage::RenderedResultCopiedBack => {
                 // commands.remove(task.target);
-                if task.should_compress {
-                    // only if feature is enabled
-                    #[cfg(feature = "compress")]
-                    {
-                        // TODO: do this in a separate thread / TaskPool
-                        let _prev_len = task.data.len();
-                        task.data = crate::compress::compress_to_basis_raw(
-                            &task.data,
-                            task.size(),
-                            task.is_srgb,
-                        );
-                        // println!("{} -> {} Kb", _prev_len / 1024, task.data.len() / 1024);
-                        task.stage = RenderToTextureTaskStage::ReadyForReading;
+                let projection = task
+                    .camera()
+                    .and_then(|camera| cameras.get(camera).ok())
+                    .map_or(Mat4::IDENTITY, Camera::projection_matrix);
+                match task.process_data(projection) {
+                    Ok(()) if task.compression.is_some() => {
+                        task.stage = RenderToTextureTaskStage::Compressing;
                     }
-                    #[cfg(not(feature = "compress"))]
-                    {
-                        panic!("Basis compression is not enabled");
+                    Ok(()) => task.stage = RenderToTextureTaskStage::ReadyForReading,
+                    Err(e) => {
+                        task.error = Some(e);
+                        task.stage = RenderToTextureTaskStage::Failed;
                     }
-                } else {
-                    task.stage = RenderToTextureTaskStage::ReadyForReading;
                 }
 
-                if !task.allow_changes {
+                task.set_camera_active(&mut cameras, false);
+
+                if task.settings.mode == RenderToTextureMode::Once {
                     task.free(&mut commands);
                 }
-
-                cameras.get_mut(task.camera.unwrap()).unwrap().is_active = false;
             }
-            RenderToTextureTaskStage::Initialized => {
-                let mut cam = cameras.get_mut(task.camera.unwrap()).unwrap();
-                assert!(
-                    !started_rendering,
-                    "Currently only one render to texture at a time is supported"
-                );
-
-                cam.is_active = true;
+            RenderToTextureTaskStage::Compressing => task.poll_compression(),
+            // an atlas without regions would deliver an empty image
+            RenderToTextureTaskStage::Initialized if !task.is_empty_atlas() => {
                 task.stage = RenderToTextureTaskStage::ReadyForRendering;
+                let bundle = commands
+                    .spawn(ImageExportBundle {
+                        source: image_exports.add(ImageExportSource {
+                            images: task.targets.clone(),
+                        }),
+                        settings: ImageExportSettings { id: (*id).into() },
+                    })
+                    .id();
+                if let Some(owner) = task.owner {
+                    commands.entity(bundle).set_parent(owner);
+                }
+                task.bundle = Some(bundle);
 
-                task.bundle = Some(
-                    commands
-                        .spawn(ImageExportBundle {
-                            source: image_exports.add(ImageExportSource {
-                                image: task.target.clone(),
-                            }),
-                            settings: crate::gpu2cpu::ImageExportSettings::default(),
-                        })
-                        .id(),
-                );
-                started_rendering = true;
+                let outputs = task.settings.extra_outputs();
+                if let Some(camera) = task.camera().filter(|_| !outputs.is_empty()) {
+                    export_view_textures(
+                        camera,
+                        ViewTextureExport {
+                            id: (*id).into(),
+                            outputs,
+                        },
+                        &mut commands,
+                    );
+                }
             }
             _ => {}
         };
 
         if task.stage == RenderToTextureTaskStage::ReadyForRendering {
-            cameras.get_mut(task.camera.unwrap()).unwrap().is_active = true;
-            //settings.get_mut(task.bundle.unwrap()).unwrap().remaining = 1;
-            extractable_images.refresh = true;
+            // `add` checks this too, but the `Msaa` resource might have changed since
+            if let Some(output) = task.settings.extra_outputs().first() {
+                if msaa.samples() > 1 {
+                    task.error = Some(RenderToTextureError::MultisampledOutput(*output));
+                    task.stage = RenderToTextureTaskStage::Failed;
+                    task.set_camera_active(&mut cameras, false);
+                    continue;
+                }
+            }
+
+            let tile = task.current_tile();
+            if task.restore_background {
+                // modifying the image uploads it to the GPU again
+                for target in &task.targets {
+                    if let Some(target) = images.get_mut(target) {
+                        fill_background(target, &task.settings, tile.min);
+                    }
+                }
+                task.restore_background = false;
+            }
+            for camera in &task.cameras {
+                if let Ok(mut projection) = tile_projections.get_mut(*camera) {
+                    if projection.tile != tile {
+                        projection.tile = tile;
+                    }
+                }
+            }
+            task.set_camera_active(&mut cameras, true);
+            extractable_images.refresh.insert((*id).into());
         }
     }
 }
 
+/// Spawns a camera that renders the given layer to a new image in every frame.
+///
+/// The layer is not handed out by [`RenderToTextureTasks`], so reserve it using
+/// [`RenderToTextureTasks::reserve_layer`] to keep tasks from rendering it.
 pub fn create_render_texture(
     width: u32,
     height: u32,
@@ -310,18 +1340,170 @@ pub fn create_render_texture(
     layer: u8,
     direct_render: bool,
 ) -> (Handle<Image>, Entity) {
-    let size = Extent3d {
-        width,
-        height,
-        ..default()
-    };
-
     let mut usage = TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC;
     if direct_render {
         usage |= TextureUsages::TEXTURE_BINDING;
     }
+    let format = if direct_render {
+        TextureFormat::Bgra8UnormSrgb
+    } else {
+        TextureFormat::Rgba8UnormSrgb
+    };
 
     // TODO: Delete the image when not in use anymore
+    let image_handle = images.add(create_render_target(
+        UVec2::new(width, height),
+        format,
+        usage,
+    ));
+    let settings = RenderToTexture::default();
+    // renders in every frame instead of being driven by a task
+    let camera = Camera {
+        is_active: true,
+        ..render_camera(image_handle.clone(), false, &settings)
+    };
+    let camera_id = spawn_render_camera(
+        camera,
+        layer,
+        &settings,
+        settings.camera,
+        settings.transform,
+        None,
+        commands,
+    );
+
+    return (image_handle, camera_id);
+}
+
+/// Checks whether a camera can render to the format and the result can be post-processed.
+fn validate_settings(settings: &RenderToTexture) -> Result<(), RenderToTextureError> {
+    if let Some(output) = settings.extra_outputs().first() {
+        // 2d cameras don't have depth or prepass textures
+        if settings.camera == RenderToTextureCamera::Camera2d {
+            return Err(RenderToTextureError::OutputUnavailable(*output));
+        }
+    }
+
+    if settings.layout != RenderToTextureLayout::Single {
+        // the per layer and per region paths are only implemented for the color image
+        if let Some(output) = settings.extra_outputs().first() {
+            return Err(RenderToTextureError::OutputUnavailable(*output));
+        }
+        if matches!(settings.camera, RenderToTextureCamera::Existing(_)) {
+            return Err(RenderToTextureError::InvalidSettings(
+                "layered textures and atlas regions can't use an existing camera".to_string(),
+            ));
+        }
+    }
+    let invalid = |reason: &str| Err(RenderToTextureError::InvalidSettings(reason.to_string()));
+    match &settings.layout {
+        RenderToTextureLayout::Cube | RenderToTextureLayout::Array(_)
+            if settings.compresses_to_basis() =>
+        {
+            return invalid("layered textures can't be compressed with basis universal");
+        }
+        RenderToTextureLayout::Cube if settings.size.x != settings.size.y => {
+            return invalid("cubemaps need a square size");
+        }
+        RenderToTextureLayout::Array(transforms) if transforms.is_empty() => {
+            return invalid("a texture array needs at least one layer");
+        }
+        #[cfg(feature = "atlas")]
+        RenderToTextureLayout::AtlasRegion(_) if settings.size.cmpeq(UVec2::ZERO).any() => {
+            return invalid("an atlas region needs a size");
+        }
+        #[cfg(feature = "atlas")]
+        RenderToTextureLayout::AtlasRegion(_) => {
+            // rendered with the settings of the atlas
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let format = settings.target_format();
+    let renderable = format
+        .guaranteed_format_features(WgpuFeatures::empty())
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT);
+    let float = matches!(
+        format.sample_type(None, None),
+        Some(TextureSampleType::Float { .. })
+    );
+    if !renderable || !float || !format.has_color_aspect() || format.is_compressed() {
+        return Err(RenderToTextureError::UnsupportedFormat(format));
+    }
+
+    // patterns are written, supersampled results scaled down and mipmaps generated on the CPU
+    let on_cpu = settings.background.is_pattern()
+        || settings.supersampling > 1
+        || settings.mip_level_count() > 1;
+    if on_cpu && !texel::is_supported(format) {
+        return Err(RenderToTextureError::UnsupportedFormat(format));
+    }
+
+    // the compressors only take 8 bit RGBA data
+    let rgba8 = matches!(
+        format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    );
+    if settings.compress && !rgba8 {
+        return Err(RenderToTextureError::UnsupportedFormat(format));
+    }
+    if settings.compresses_to_basis() {
+        settings
+            .basis
+            .validate()
+            .map_err(RenderToTextureError::InvalidSettings)?;
+        // bevy can't load basis LZ supercompressed KTX2 files
+        let etc1s = matches!(settings.basis.format, BasisCompressionFormat::Etc1s { .. });
+        if etc1s && settings.container != RenderToTextureContainer::Raw {
+            return invalid("ETC1S compressed results can't be written to KTX2");
+        }
+    }
+    // the base level of block compressed textures consists of whole blocks
+    if settings.compress
+        && matches!(settings.compressor, RenderToTextureCompressor::Bcn(_))
+        && (settings.size.x % 4 != 0 || settings.size.y % 4 != 0)
+    {
+        return invalid("BCn compression needs a size that is a multiple of 4");
+    }
+
+    Ok(())
+}
+
+/// Checks whether a task that exceeds the limits of the device can be rendered in tiles.
+fn validate_tiling(
+    settings: &RenderToTexture,
+    tiles: &TileGrid,
+) -> Result<(), RenderToTextureError> {
+    let tileable = settings.layout == RenderToTextureLayout::Single
+        && !matches!(settings.camera, RenderToTextureCamera::Existing(_))
+        && settings.extra_outputs().is_empty();
+    if tiles.is_tiled() && !tileable {
+        let size = settings.render_size();
+        return Err(RenderToTextureError::InvalidSettings(format!(
+            "{}x{} pixels exceed the limits of the device, but only single textures without \
+             extra outputs rendered by a camera of the task can be tiled",
+            size.x, size.y
+        )));
+    }
+    Ok(())
+}
+
+/// Whether the format has more precision than the 8 bit main texture of an LDR camera.
+fn needs_hdr(format: TextureFormat) -> bool {
+    format
+        .block_copy_size(None)
+        .is_some_and(|size| size > format.components() as u32)
+}
+
+/// Creates the image a task camera renders to.
+fn create_render_target(size: UVec2, format: TextureFormat, usage: TextureUsages) -> Image {
+    let size = Extent3d {
+        width: size.x,
+        height: size.y,
+        ..default()
+    };
 
     // This is the texture that will be rendered to.
     let mut image = Image {
@@ -329,11 +1511,7 @@ pub fn create_render_texture(
             label: None,
             size,
             dimension: TextureDimension::D2,
-            format: if direct_render {
-                TextureFormat::Bgra8UnormSrgb
-            } else {
-                TextureFormat::Rgba8UnormSrgb
-            },
+            format,
             mip_level_count: 1,
             sample_count: 1,
             usage,
@@ -345,24 +1523,470 @@ pub fn create_render_texture(
     // fill image.data with zeroes
     image.resize(size);
 
-    let image_handle = images.add(image);
-
-    let camera_id = commands
-        .spawn((
-            Camera2dBundle {
-                camera_2d: Camera2d { ..default() },
-                camera: Camera {
-                    // render before the "main pass" camera
-                    order: -1,
-                    clear_color: ClearColorConfig::Custom(Color::rgba(0.0, 0.0, 0.0, 0.0)),
-                    target: image_handle.clone().into(),
-                    ..default()
+    image
+}
+
+/// Makes an existing camera render to the target of a task.
+fn retarget_camera(
+    camera: Entity,
+    target: Handle<Image>,
+    layer: u8,
+    hdr: bool,
+    transform: Option<Transform>,
+    commands: &mut Commands,
+) {
+    commands.add(move |world: &mut World| {
+        let Some(mut entity) = world.get_entity_mut(camera) else {
+            warn!(
+                "The camera {:?} of a render to texture task doesn't exist",
+                camera
+            );
+            return;
+        };
+        let Some(mut camera) = entity.get_mut::<Camera>() else {
+            warn!(
+                "The entity {:?} of a render to texture task is not a camera",
+                camera
+            );
+            return;
+        };
+        camera.target = target.into();
+        camera.hdr |= hdr;
+        // the task activates the camera when it is ready for rendering
+        camera.is_active = false;
+
+        if !entity.contains::<RenderLayers>() {
+            entity.insert(RenderLayers::layer(layer));
+        }
+        if let Some(transform) = transform {
+            entity.insert(transform);
+        }
+    });
+}
+
+/// Makes the camera's depth texture copyable, enables the required prepasses
+/// and requests the view textures to be read back.
+fn export_view_textures(camera: Entity, export: ViewTextureExport, commands: &mut Commands) {
+    commands.add(move |world: &mut World| {
+        let Some(mut entity) = world.get_entity_mut(camera) else {
+            return;
+        };
+        if let Some(mut camera_3d) = entity.get_mut::<Camera3d>() {
+            let usages = TextureUsages::from(camera_3d.depth_texture_usages);
+            camera_3d.depth_texture_usages = (usages | TextureUsages::COPY_SRC).into();
+        }
+        for output in &export.outputs {
+            match output {
+                RenderToTextureOutput::Normal => {
+                    entity.insert(NormalPrepass);
+                }
+                RenderToTextureOutput::MotionVectors => {
+                    entity.insert(MotionVectorPrepass);
+                }
+                RenderToTextureOutput::Color | RenderToTextureOutput::Depth => {}
+            }
+        }
+        entity.insert(export);
+    });
+}
+
+/// Post-processes the data of an output besides the color image: linearizes depth if requested
+/// and scales it to the size of the result.
+fn process_output(
+    output: RenderToTextureOutput,
+    data: &[u8],
+    settings: &RenderToTexture,
+    projection: Mat4,
+) -> Result<Vec<u8>, RenderToTextureError> {
+    let format = output.format().unwrap_or(settings.target_format());
+    let texel_size = format.block_copy_size(None).unwrap_or(0) as usize;
+    let render_size = settings.render_size();
+
+    // multisampled or missing textures are delivered without data
+    if data.len() != (render_size.x * render_size.y) as usize * texel_size {
+        return Err(RenderToTextureError::OutputUnavailable(output));
+    }
+
+    let mut data = data.to_vec();
+    if output == RenderToTextureOutput::Depth
+        && settings.depth == Some(RenderToTextureDepth::Linear)
+    {
+        let inverse = projection.inverse();
+        for texel in data.chunks_exact_mut(4) {
+            let depth = f32::from_le_bytes(texel.try_into().unwrap());
+            // the view space z of a point at the depth; the camera looks along -z
+            let view = inverse * Vec4::new(0.0, 0.0, depth, 1.0);
+            let distance = if view.w == 0.0 {
+                f32::INFINITY
+            } else {
+                -view.z / view.w
+            };
+            texel.copy_from_slice(&distance.to_le_bytes());
+        }
+    }
+
+    if settings.supersampling > 1 {
+        data = resample::resize_data(
+            &data,
+            format,
+            render_size,
+            settings.size,
+            settings.downsample_filter,
+        )
+        .ok_or(RenderToTextureError::UnsupportedFormat(format))?;
+    }
+    Ok(data)
+}
+
+/// Writes the background pattern, if any, to the image.
+/// `offset` is the position of the image in the render target, e.g., of a tile.
+fn fill_background(image: &mut Image, settings: &RenderToTexture, offset: UVec2) {
+    let background = &settings.background;
+    if !background.is_pattern() {
+        return;
+    }
+    let width = image.width();
+    let factor = settings.supersampling.max(1);
+    let format = image.texture_descriptor.format;
+    let texel_size = format.block_copy_size(None).unwrap_or(0) as usize;
+    for (i, texel) in image.data.chunks_exact_mut(texel_size).enumerate() {
+        // the pattern is defined in pixels of the final image
+        let (x, y) = (i as u32 % width + offset.x, i as u32 / width + offset.y);
+        let (x, y) = (x / factor, y / factor);
+        let Some(color) = background.color_at(x, y, settings.size) else {
+            continue;
+        };
+        let color = Vec4::from_array(color.as_linear_rgba_f32());
+        if let Some(bytes) = texel::encode(format, color) {
+            texel.copy_from_slice(&bytes);
+        }
+    }
+}
+
+/// Mirrors each row of the texel data, e.g., to turn a rendered cube face into the
+/// left-handed orientation cubemaps are sampled with.
+fn mirror_horizontally(data: &mut [u8], width: u32, format: TextureFormat) {
+    let texel_size = format.block_copy_size(None).unwrap_or(0) as usize;
+    let row_size = width as usize * texel_size;
+    if row_size == 0 {
+        return;
+    }
+    for row in data.chunks_exact_mut(row_size) {
+        for x in 0..width as usize / 2 {
+            let mirrored = width as usize - 1 - x;
+            let (left, right) = row.split_at_mut(mirrored * texel_size);
+            left[x * texel_size..(x + 1) * texel_size].swap_with_slice(&mut right[..texel_size]);
+        }
+    }
+}
+
+/// Creates the camera component of a task camera rendering to the target.
+fn render_camera(target: Handle<Image>, hdr: bool, settings: &RenderToTexture) -> Camera {
+    let mut camera = Camera {
+        // activated by `update_render_to_texture` once the task is ready for rendering
+        is_active: false,
+        // render before the "main pass" camera
+        order: -1,
+        hdr,
+        clear_color: ClearColorConfig::Custom(Color::NONE),
+        target: target.into(),
+        ..default()
+    };
+    match settings.background {
+        RenderToTextureBackground::Color(color) => {
+            camera.clear_color = ClearColorConfig::Custom(color);
+        }
+        _ => {
+            // the intermediate texture is cleared, so blend it over what is already in the target
+            camera.output_mode = CameraOutputMode::Write {
+                blend_state: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                color_attachment_load_op: LoadOp::Load,
+            };
+        }
+    }
+    camera
+}
+
+/// Spawns an inactive camera of the given kind that renders the given layer.
+/// With a `tile`, the camera only renders that part of the image.
+fn spawn_render_camera(
+    camera: Camera,
+    layer: u8,
+    settings: &RenderToTexture,
+    kind: RenderToTextureCamera,
+    transform: Option<Transform>,
+    tile: Option<URect>,
+    commands: &mut Commands,
+) -> Entity {
+    let projection = match kind {
+        RenderToTextureCamera::Camera2d => Projection::Orthographic(OrthographicProjection {
+            // show the same area on the larger target
+            scale: 1.0 / settings.supersampling.max(1) as f32,
+            ..Camera2dBundle::default().projection
+        }),
+        RenderToTextureCamera::Perspective { fov, near, far } => {
+            Projection::Perspective(PerspectiveProjection {
+                fov,
+                near,
+                far,
+                ..default()
+            })
+        }
+        RenderToTextureCamera::Orthographic { area, near, far } => {
+            let size = area.size();
+            Projection::Orthographic(OrthographicProjection {
+                near,
+                far,
+                scaling_mode: ScalingMode::Fixed {
+                    width: size.x,
+                    height: size.y,
                 },
+                // places `area.min` in the bottom left corner of the viewport
+                viewport_origin: -area.min / size,
+                ..default()
+            })
+        }
+        RenderToTextureCamera::Existing(_) => {
+            unreachable!("Existing cameras are retargeted, not spawned")
+        }
+    };
+
+    let mut entity = match (kind, projection.clone()) {
+        (RenderToTextureCamera::Camera2d, Projection::Orthographic(projection)) => {
+            commands.spawn(Camera2dBundle {
+                camera,
+                projection,
                 ..default()
+            })
+        }
+        (_, projection) => commands.spawn(Camera3dBundle {
+            camera,
+            projection,
+            // like the 2d camera, don't alter the rendered colors
+            tonemapping: Tonemapping::None,
+            dither: DebandDither::Disabled,
+            ..default()
+        }),
+    };
+    if let Some(tile) = tile {
+        entity
+            .remove::<(Projection, OrthographicProjection)>()
+            .insert(TileProjection {
+                base: projection,
+                size: settings.render_size(),
+                tile,
+            });
+    }
+    entity.insert(RenderLayers::layer(layer));
+    if let Some(transform) = transform {
+        entity.insert(transform);
+    }
+    entity.id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn setup(msaa: Msaa) -> World {
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<Assets<ImageExportSource>>();
+        world.init_resource::<ExtractableImages>();
+        world.insert_resource(msaa);
+        world.init_resource::<RenderToTextureTasks>();
+        world.run_system_once(sync_msaa);
+        world
+    }
+
+    fn add(world: &mut World, settings: RenderToTexture) -> RenderToTextureTaskId {
+        world.run_system_once_with(
+            settings,
+            |In(settings): In<RenderToTexture>,
+             mut tasks: ResMut<RenderToTextureTasks>,
+             mut commands: Commands,
+             mut images: ResMut<Assets<Image>>| {
+                tasks.add(None, settings, &mut commands, &mut images)
             },
-            RenderLayers::layer(layer),
-        ))
-        .id();
+        )
+    }
 
-    return (image_handle, camera_id);
+    fn depth_settings() -> RenderToTexture {
+        RenderToTexture {
+            camera: RenderToTextureCamera::perspective(),
+            depth: Some(RenderToTextureDepth::Raw),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn outputs_fail_when_added_with_msaa() {
+        let mut world = setup(Msaa::Sample4);
+        let id = add(&mut world, depth_settings());
+        let tasks = world.resource::<RenderToTextureTasks>();
+        let task = tasks.get(id).unwrap();
+        assert!(task.stage == RenderToTextureTaskStage::Failed);
+        assert_eq!(
+            task.error(),
+            Some(&RenderToTextureError::MultisampledOutput(
+                RenderToTextureOutput::Depth
+            ))
+        );
+        // the task didn't take a layer or spawn a camera
+        assert!(task.cameras().is_empty());
+        assert!(!tasks.layers.is_allocated(1));
+    }
+
+    #[test]
+    fn outputs_render_without_msaa() {
+        let mut world = setup(Msaa::Off);
+        let id = add(&mut world, depth_settings());
+        let task = world.resource::<RenderToTextureTasks>().get(id).unwrap();
+        assert!(task.stage == RenderToTextureTaskStage::Initialized);
+        assert_eq!(task.cameras().len(), 1);
+        assert_eq!(task.get_layer(), RenderLayers::layer(1));
+    }
+
+    #[test]
+    fn tasks_fail_without_free_layer() {
+        let mut world = setup(Msaa::Off);
+        let ids: Vec<_> = (0..RenderLayerAllocator::TOTAL_LAYERS)
+            .map(|_| add(&mut world, RenderToTexture::default()))
+            .collect();
+        let tasks = world.resource::<RenderToTextureTasks>();
+        // layer 0 is never handed out, so the last task is left without one
+        for id in &ids[..ids.len() - 1] {
+            assert!(tasks.get(*id).unwrap().error().is_none());
+        }
+        let last = tasks.get(*ids.last().unwrap()).unwrap();
+        assert_eq!(last.error(), Some(&RenderToTextureError::NoFreeLayer));
+        assert!(last.cameras().is_empty());
+    }
+
+    #[test]
+    fn requested_outputs_are_delivered() {
+        let mut world = setup(Msaa::Off);
+        let settings = RenderToTexture {
+            size: UVec2::new(4, 4),
+            camera: RenderToTextureCamera::perspective(),
+            depth: Some(RenderToTextureDepth::Raw),
+            normals: true,
+            motion_vectors: true,
+            ..default()
+        };
+        let requested = settings.extra_outputs();
+        assert_eq!(
+            requested,
+            [
+                RenderToTextureOutput::Depth,
+                RenderToTextureOutput::Normal,
+                RenderToTextureOutput::MotionVectors
+            ]
+        );
+        let id = add(&mut world, settings);
+
+        // the camera exports exactly the requested outputs
+        world.run_system_once(update_render_to_texture);
+        let camera = world
+            .resource::<RenderToTextureTasks>()
+            .get(id)
+            .unwrap()
+            .cameras()[0];
+        let export = world.get::<ViewTextureExport>(camera).unwrap();
+        assert_eq!(export.outputs, requested);
+        assert!(world.get::<NormalPrepass>(camera).is_some());
+        assert!(world.get::<MotionVectorPrepass>(camera).is_some());
+
+        // all supported formats have 4 bytes per texel
+        let mut extractable = world.resource_mut::<ExtractableImages>();
+        for output in requested.iter().chain([&RenderToTextureOutput::Color]) {
+            let key = (ExportId::from(id), *output);
+            extractable.raw.insert(key, vec![0; 4 * 4 * 4]);
+        }
+        world.run_system_once(update_render_to_texture);
+
+        let results = world.resource_scope(|world, mut images: Mut<Assets<Image>>| {
+            let mut tasks = world.resource_mut::<RenderToTextureTasks>();
+            tasks.collect_results(&mut images)
+        });
+        let [Ok(finished)] = results.as_slice() else {
+            panic!("the task didn't finish");
+        };
+        let mut outputs: Vec<_> = finished.outputs.keys().copied().collect();
+        outputs.sort_by_key(|output| requested.iter().position(|o| o == output));
+        assert_eq!(outputs, requested);
+    }
+
+    #[cfg(feature = "atlas")]
+    #[test]
+    fn atlas_regions_share_the_atlas_layer() {
+        let mut world = setup(Msaa::Off);
+        let atlas = RenderToTexture {
+            size: UVec2::new(64, 64),
+            layout: RenderToTextureLayout::Atlas { padding: 0 },
+            ..default()
+        };
+        let atlas = add(&mut world, atlas);
+
+        // more regions than there are render layers
+        let regions: Vec<_> = (0..RenderLayerAllocator::TOTAL_LAYERS + 8)
+            .map(|_| {
+                let region = RenderToTexture {
+                    size: UVec2::new(8, 8),
+                    layout: RenderToTextureLayout::AtlasRegion(atlas),
+                    ..default()
+                };
+                add(&mut world, region)
+            })
+            .collect();
+        let tasks = world.resource::<RenderToTextureTasks>();
+        let layer = tasks.get(atlas).unwrap().get_layer();
+        for region in &regions {
+            let region = tasks.get(*region).unwrap();
+            assert!(region.error().is_none());
+            assert_eq!(region.get_layer(), layer);
+        }
+        assert!(!tasks.layers.is_allocated(2));
+
+        // removing a region leaves the layer to the atlas
+        world.run_system_once_with(
+            regions[0],
+            |In(id), mut tasks: ResMut<RenderToTextureTasks>, mut commands: Commands| {
+                tasks.free(id, &mut commands);
+            },
+        );
+        assert!(world
+            .resource::<RenderToTextureTasks>()
+            .layers
+            .is_allocated(1));
+    }
+
+    #[cfg(feature = "atlas")]
+    #[test]
+    fn empty_atlas_waits_for_regions() {
+        let mut world = setup(Msaa::Off);
+        let settings = RenderToTexture {
+            size: UVec2::new(64, 64),
+            layout: RenderToTextureLayout::Atlas { padding: 0 },
+            ..default()
+        };
+        let atlas = add(&mut world, settings);
+        let stage = |world: &World| {
+            let tasks = world.resource::<RenderToTextureTasks>();
+            tasks.get(atlas).unwrap().stage.clone()
+        };
+
+        world.run_system_once(update_render_to_texture);
+        assert!(stage(&world) == RenderToTextureTaskStage::Initialized);
+
+        let region = RenderToTexture {
+            size: UVec2::new(8, 8),
+            layout: RenderToTextureLayout::AtlasRegion(atlas),
+            ..default()
+        };
+        add(&mut world, region);
+        world.run_system_once(update_render_to_texture);
+        assert!(stage(&world) == RenderToTextureTaskStage::ReadyForRendering);
+    }
 }
@@ -0,0 +1,383 @@
+use crate::texel;
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+use std::f32::consts::PI;
+
+/// Filter used when an image is scaled down on the CPU.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum RenderToTextureFilter {
+    /// Averages all covered pixels. Fast and without ringing.
+    #[default]
+    Box,
+    /// A windowed sinc with 3 lobes. Sharper, but can overshoot at hard edges.
+    Lanczos3,
+}
+
+impl RenderToTextureFilter {
+    /// Weights of the source pixels contributing to each target pixel along one axis.
+    fn weights(&self, source: u32, target: u32) -> Vec<(usize, Vec<f32>)> {
+        let scale = source as f32 / target as f32;
+        // stretch the kernel when scaling down to avoid aliasing
+        let stretch = scale.max(1.0);
+        (0..target)
+            .map(|x| {
+                let (start, end) = (x as f32 * scale, (x + 1) as f32 * scale);
+                let center = (start + end) / 2.0;
+                let (first, last) = match self {
+                    Self::Box => (start, end),
+                    Self::Lanczos3 => (center - 3.0 * stretch - 0.5, center + 3.0 * stretch + 0.5),
+                };
+                let first = first.floor().max(0.0) as usize;
+                let last = (last.ceil().max(0.0) as usize).min(source as usize);
+
+                let mut weights: Vec<f32> = (first..last)
+                    .map(|i| {
+                        let i = i as f32;
+                        match self {
+                            // overlap of the source pixel with the footprint of the target pixel
+                            Self::Box => (end.min(i + 1.0) - start.max(i)).max(0.0),
+                            Self::Lanczos3 => lanczos3((i + 0.5 - center) / stretch),
+                        }
+                    })
+                    .collect();
+                let sum: f32 = weights.iter().sum();
+                if sum != 0.0 {
+                    weights.iter_mut().for_each(|w| *w /= sum);
+                }
+                (first, weights)
+            })
+            .collect()
+    }
+}
+
+fn lanczos3(x: f32) -> f32 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= 3.0 {
+        return 0.0;
+    }
+    let x = x * PI;
+    3.0 * x.sin() * (x / 3.0).sin() / (x * x)
+}
+
+/// Decodes texel data to premultiplied linear colors.
+pub fn decode(data: &[u8], format: TextureFormat) -> Option<Vec<Vec4>> {
+    let texel_size = format.block_copy_size(None)? as usize;
+    data.chunks_exact(texel_size)
+        .map(|texel| {
+            let color = texel::decode(format, texel)?;
+            Some((color.truncate() * color.w).extend(color.w))
+        })
+        .collect()
+}
+
+/// Encodes premultiplied linear colors as texel data.
+pub fn encode(colors: &[Vec4], format: TextureFormat) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(colors.len() * format.block_copy_size(None)? as usize);
+    for color in colors {
+        let color = if color.w > 0.0 {
+            (color.truncate() / color.w).extend(color.w)
+        } else {
+            Vec4::ZERO
+        };
+        data.extend(texel::encode(format, color)?);
+    }
+    Some(data)
+}
+
+/// Resizes premultiplied colors using a separable filter.
+pub fn resize(
+    colors: &[Vec4],
+    size: UVec2,
+    target: UVec2,
+    filter: RenderToTextureFilter,
+) -> Vec<Vec4> {
+    let horizontal = filter.weights(size.x, target.x);
+    let vertical = filter.weights(size.y, target.y);
+
+    let mut rows = vec![Vec4::ZERO; (target.x * size.y) as usize];
+    for y in 0..size.y as usize {
+        let source = &colors[y * size.x as usize..(y + 1) * size.x as usize];
+        for (x, (first, weights)) in horizontal.iter().enumerate() {
+            rows[y * target.x as usize + x] = weights
+                .iter()
+                .zip(&source[*first..])
+                .map(|(w, c)| *c * *w)
+                .sum();
+        }
+    }
+
+    let mut result = vec![Vec4::ZERO; (target.x * target.y) as usize];
+    for (y, (first, weights)) in vertical.iter().enumerate() {
+        for x in 0..target.x as usize {
+            result[y * target.x as usize + x] = weights
+                .iter()
+                .enumerate()
+                .map(|(i, w)| rows[(first + i) * target.x as usize + x] * *w)
+                .sum();
+        }
+    }
+    result
+}
+
+/// Number of mip levels of a full mip chain down to 1x1 pixels.
+pub fn mip_level_count(size: UVec2) -> u32 {
+    32 - size.max_element().max(1).leading_zeros()
+}
+
+/// Appends the smaller mip levels to the texel data of an image, each half the size of the
+/// previous one. Returns `None` if the format isn't supported.
+pub fn mip_chain(data: &[u8], format: TextureFormat, size: UVec2, levels: u32) -> Option<Vec<u8>> {
+    let mut colors = decode(data, format)?;
+    let mut size = size;
+    let mut chain = data.to_vec();
+    for _ in 1..levels {
+        let target = (size / 2).max(UVec2::ONE);
+        // each level is filtered from the previous one, like the GPU would
+        colors = resize(&colors, size, target, RenderToTextureFilter::Box);
+        chain.extend(encode(&colors, format)?);
+        size = target;
+    }
+    Some(chain)
+}
+
+/// Appends the mip levels of each layer to the layer, for the texel data of `layers` layers
+/// of the given size. Returns `None` if the format isn't supported.
+pub fn layered_mip_chain(
+    data: &[u8],
+    format: TextureFormat,
+    size: UVec2,
+    levels: u32,
+    layers: u32,
+) -> Option<Vec<u8>> {
+    let layer_size = data.len() / layers.max(1) as usize;
+    let mut chain = Vec::new();
+    for layer in data.chunks(layer_size.max(1)) {
+        chain.extend(mip_chain(layer, format, size, levels)?);
+    }
+    Some(chain)
+}
+
+/// Resizes texel data of the given format. Returns `None` if the format isn't supported.
+pub fn resize_data(
+    data: &[u8],
+    format: TextureFormat,
+    size: UVec2,
+    target: UVec2,
+    filter: RenderToTextureFilter,
+) -> Option<Vec<u8>> {
+    let colors = decode(data, format)?;
+    encode(&resize(&colors, size, target, filter), format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [RenderToTextureFilter; 2] =
+        [RenderToTextureFilter::Box, RenderToTextureFilter::Lanczos3];
+
+    /// A deterministic image with varying colors and alpha.
+    fn pattern(size: UVec2) -> Vec<Vec4> {
+        (0..size.x * size.y)
+            .map(|i| {
+                let alpha = (i % 5) as f32 / 4.0;
+                Vec4::new(
+                    (i % 7) as f32 / 6.0 * alpha,
+                    (i % 3) as f32 / 2.0 * alpha,
+                    (i % 11) as f32 / 10.0 * alpha,
+                    alpha,
+                )
+            })
+            .collect()
+    }
+
+    fn assert_close(a: &[Vec4], b: &[Vec4], tolerance: f32) {
+        assert_eq!(a.len(), b.len());
+        for (i, (a, b)) in a.iter().zip(b).enumerate() {
+            assert!(
+                (*a - *b).abs().max_element() <= tolerance,
+                "pixel {i}: {a} != {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn identity_size() {
+        let size = UVec2::new(7, 5);
+        let colors = pattern(size);
+        for filter in FILTERS {
+            assert_close(&resize(&colors, size, size, filter), &colors, 1e-5);
+        }
+    }
+
+    #[test]
+    fn constant_image() {
+        let color = Vec4::new(0.2, 0.4, 0.1, 0.5);
+        let size = UVec2::new(13, 7);
+        let colors = vec![color; (size.x * size.y) as usize];
+        for filter in FILTERS {
+            for target in [UVec2::new(4, 3), UVec2::new(1, 1), UVec2::new(26, 14)] {
+                let resized = resize(&colors, size, target, filter);
+                let expected = vec![color; (target.x * target.y) as usize];
+                assert_close(&resized, &expected, 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn box_weights_cover_partial_pixels() {
+        // the first target pixel covers 1.5 source pixels
+        let colors = [0.0, 0.3, 0.6].map(Vec4::splat);
+        let resized = resize(
+            &colors,
+            UVec2::new(3, 1),
+            UVec2::new(2, 1),
+            RenderToTextureFilter::Box,
+        );
+        assert_close(&resized, &[Vec4::splat(0.1), Vec4::splat(0.5)], 1e-6);
+    }
+
+    #[test]
+    fn lanczos3_kernel() {
+        assert_eq!(lanczos3(0.0), 1.0);
+        for x in [1.0, 2.0, -1.0, -2.0] {
+            assert!(lanczos3(x).abs() < 1e-6);
+        }
+        assert_eq!(lanczos3(3.0), 0.0);
+        assert_eq!(lanczos3(-4.5), 0.0);
+        // the negative lobe that makes the filter sharper
+        assert!(lanczos3(1.5) < 0.0);
+    }
+
+    #[test]
+    fn premultiplied_round_trip() {
+        let format = TextureFormat::Rgba8Unorm;
+        let data = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0];
+        let colors = decode(&data, format).unwrap();
+        assert_close(
+            &colors,
+            &[
+                Vec4::new(1.0, 0.0, 0.0, 1.0),
+                Vec4::new(0.0, 128.0 / 255.0, 0.0, 128.0 / 255.0),
+                Vec4::ZERO,
+            ],
+            1e-6,
+        );
+        // fully transparent texels lose their color
+        assert_eq!(
+            encode(&colors, format).unwrap(),
+            [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn alpha_edge() {
+        // opaque red next to transparent green, which must not bleed into the result
+        let format = TextureFormat::Rgba8Unorm;
+        let data = [255, 0, 0, 255, 255, 0, 0, 255, 0, 255, 0, 0, 0, 255, 0, 0];
+        for filter in FILTERS {
+            let resized =
+                resize_data(&data, format, UVec2::new(4, 1), UVec2::new(2, 1), filter).unwrap();
+            assert_eq!(resized[1], 0, "{filter:?}");
+            assert_eq!(resized[5], 0, "{filter:?}");
+            // the color of partially covered texels stays red
+            for texel in resized.chunks_exact(4).filter(|texel| texel[3] > 0) {
+                assert_eq!(texel[0], 255, "{filter:?}");
+            }
+        }
+
+        let resized = resize_data(
+            &data,
+            format,
+            UVec2::new(4, 1),
+            UVec2::new(1, 1),
+            RenderToTextureFilter::Box,
+        )
+        .unwrap();
+        assert_eq!(resized, [255, 0, 0, 128]);
+    }
+
+    #[test]
+    fn mip_level_counts() {
+        assert_eq!(mip_level_count(UVec2::ONE), 1);
+        assert_eq!(mip_level_count(UVec2::ZERO), 1);
+        assert_eq!(mip_level_count(UVec2::splat(256)), 9);
+        // the longer side determines the count
+        assert_eq!(mip_level_count(UVec2::new(256, 16)), 9);
+        assert_eq!(mip_level_count(UVec2::new(1, 64)), 7);
+        // sizes that aren't powers of two are rounded down at each level
+        assert_eq!(mip_level_count(UVec2::splat(255)), 8);
+        assert_eq!(mip_level_count(UVec2::new(257, 3)), 9);
+        assert_eq!(mip_level_count(UVec2::new(100, 60)), 7);
+    }
+
+    /// Number of texels of a mip chain, with each level half the size of the previous one.
+    fn chain_texels(size: UVec2, levels: u32) -> usize {
+        (0..levels)
+            .map(|level| (size >> level).max(UVec2::ONE))
+            .map(|size| (size.x * size.y) as usize)
+            .sum()
+    }
+
+    #[test]
+    fn mip_chain_lengths() {
+        let format = TextureFormat::Rgba8Unorm;
+        for size in [
+            UVec2::splat(8),
+            UVec2::new(16, 4),
+            UVec2::new(3, 10),
+            UVec2::new(7, 5),
+        ] {
+            let levels = mip_level_count(size);
+            let data = vec![128; (size.x * size.y) as usize * 4];
+            let chain = mip_chain(&data, format, size, levels).unwrap();
+            assert_eq!(chain.len(), chain_texels(size, levels) * 4, "{size}");
+            // the base level is kept as it is and the levels of a constant image stay constant
+            assert_eq!(chain[..data.len()], data);
+            assert_eq!(chain[chain.len() - 4..], [128; 4]);
+        }
+        assert_eq!(
+            mip_chain(&[1, 2, 3, 4], format, UVec2::ONE, 1).unwrap(),
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn layered_mip_chain_lengths() {
+        let format = TextureFormat::Rgba8Unorm;
+        let size = UVec2::new(6, 3);
+        let levels = mip_level_count(size);
+        let layer_texels = (size.x * size.y) as usize;
+        for layers in [1, 2, 6] {
+            // each layer has its own color
+            let data: Vec<u8> = (0..layers)
+                .flat_map(|layer| vec![layer as u8 * 40; layer_texels * 4])
+                .collect();
+            let chain = layered_mip_chain(&data, format, size, levels, layers).unwrap();
+            let chain_size = chain_texels(size, levels) * 4;
+            assert_eq!(chain.len(), chain_size * layers as usize, "{layers} layers");
+            // the levels of each layer follow the layer
+            for (layer, levels) in chain.chunks(chain_size).enumerate() {
+                assert!(
+                    levels.iter().all(|c| *c == layer as u8 * 40),
+                    "layer {layer}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let size = UVec2::new(4, 4);
+        let data = vec![0; 16 * 4];
+        assert!(resize_data(
+            &data,
+            TextureFormat::Depth32Float,
+            size,
+            size / 2,
+            RenderToTextureFilter::Box
+        )
+        .is_none());
+    }
+}
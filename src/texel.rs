@@ -0,0 +1,354 @@
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+
+/// Whether texels of the format can be converted from and to colors on the CPU.
+pub fn is_supported(format: TextureFormat) -> bool {
+    encode(format, Vec4::ZERO).is_some()
+}
+
+/// Number of color channels stored per texel.
+fn channels(format: TextureFormat) -> usize {
+    format.components() as usize
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // infinity or NaN
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // subnormal
+        let mantissa = (mantissa | 0x80_0000) >> (1 - exponent);
+        return sign | ((mantissa + 0x1000) >> 13) as u16;
+    }
+    // rounding might carry into the exponent, which is still correct
+    sign | (((exponent as u32) << 10) + ((mantissa + 0x1000) >> 13)) as u16
+}
+
+fn f16_to_f32(value: u16) -> f32 {
+    let sign = ((value & 0x8000) as u32) << 16;
+    let exponent = ((value >> 10) & 0x1f) as u32;
+    let mantissa = (value & 0x3ff) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // subnormal: normalize the mantissa
+            let shift = mantissa.leading_zeros() - 21;
+            let mantissa = (mantissa << shift) & 0x3ff;
+            sign | ((127 - 15 + 1 - shift) << 23) | (mantissa << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+fn unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn unorm16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
+/// Encodes a linear RGBA color as a single texel of the format.
+///
+/// The color is stored the way a shader writing it would store it,
+/// i.e., sRGB formats get sRGB encoded values and channels the format lacks are dropped.
+pub fn encode(format: TextureFormat, color: Vec4) -> Option<Vec<u8>> {
+    let c = color.to_array();
+    let srgb = [
+        linear_to_srgb(c[0]),
+        linear_to_srgb(c[1]),
+        linear_to_srgb(c[2]),
+        c[3],
+    ];
+    let n = channels(format);
+
+    let bytes = match format {
+        TextureFormat::R8Unorm | TextureFormat::Rg8Unorm | TextureFormat::Rgba8Unorm => {
+            c[..n].iter().map(|v| unorm8(*v)).collect()
+        }
+        TextureFormat::Rgba8UnormSrgb => srgb.iter().map(|v| unorm8(*v)).collect(),
+        TextureFormat::Bgra8Unorm => [c[2], c[1], c[0], c[3]].map(unorm8).to_vec(),
+        TextureFormat::Bgra8UnormSrgb => [srgb[2], srgb[1], srgb[0], srgb[3]].map(unorm8).to_vec(),
+        TextureFormat::R16Unorm | TextureFormat::Rg16Unorm | TextureFormat::Rgba16Unorm => c[..n]
+            .iter()
+            .flat_map(|v| unorm16(*v).to_le_bytes())
+            .collect(),
+        TextureFormat::R16Float | TextureFormat::Rg16Float | TextureFormat::Rgba16Float => c[..n]
+            .iter()
+            .flat_map(|v| f32_to_f16(*v).to_le_bytes())
+            .collect(),
+        TextureFormat::R32Float | TextureFormat::Rg32Float | TextureFormat::Rgba32Float => {
+            c[..n].iter().flat_map(|v| v.to_le_bytes()).collect()
+        }
+        TextureFormat::Rgb10a2Unorm => {
+            let channel = |v: f32, max: f32| (v.clamp(0.0, 1.0) * max).round() as u32;
+            let packed = channel(c[0], 1023.0)
+                | (channel(c[1], 1023.0) << 10)
+                | (channel(c[2], 1023.0) << 20)
+                | (channel(c[3], 3.0) << 30);
+            packed.to_le_bytes().to_vec()
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Decodes a single texel of the format to a linear RGBA color.
+///
+/// Missing color channels are 0 and a missing alpha channel is 1.
+pub fn decode(format: TextureFormat, bytes: &[u8]) -> Option<Vec4> {
+    let mut c = [0.0, 0.0, 0.0, 1.0];
+    let n = channels(format);
+
+    match format {
+        TextureFormat::R8Unorm | TextureFormat::Rg8Unorm | TextureFormat::Rgba8Unorm => {
+            for i in 0..n {
+                c[i] = bytes[i] as f32 / 255.0;
+            }
+        }
+        TextureFormat::Rgba8UnormSrgb => {
+            for i in 0..4 {
+                c[i] = bytes[i] as f32 / 255.0;
+            }
+            for v in &mut c[..3] {
+                *v = srgb_to_linear(*v);
+            }
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            c = [bytes[2], bytes[1], bytes[0], bytes[3]].map(|v| v as f32 / 255.0);
+            if format.is_srgb() {
+                for v in &mut c[..3] {
+                    *v = srgb_to_linear(*v);
+                }
+            }
+        }
+        TextureFormat::R16Unorm | TextureFormat::Rg16Unorm | TextureFormat::Rgba16Unorm => {
+            for i in 0..n {
+                c[i] = u16::from_le_bytes([bytes[2 * i], bytes[2 * i + 1]]) as f32 / 65535.0;
+            }
+        }
+        TextureFormat::R16Float | TextureFormat::Rg16Float | TextureFormat::Rgba16Float => {
+            for i in 0..n {
+                c[i] = f16_to_f32(u16::from_le_bytes([bytes[2 * i], bytes[2 * i + 1]]));
+            }
+        }
+        TextureFormat::R32Float | TextureFormat::Rg32Float | TextureFormat::Rgba32Float => {
+            for i in 0..n {
+                c[i] = f32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap());
+            }
+        }
+        TextureFormat::Rgb10a2Unorm => {
+            let packed = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+            c = [
+                (packed & 0x3ff) as f32 / 1023.0,
+                ((packed >> 10) & 0x3ff) as f32 / 1023.0,
+                ((packed >> 20) & 0x3ff) as f32 / 1023.0,
+                (packed >> 30) as f32 / 3.0,
+            ];
+        }
+        _ => return None,
+    }
+    Some(Vec4::from_array(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [TextureFormat; 16] = [
+        TextureFormat::R8Unorm,
+        TextureFormat::Rg8Unorm,
+        TextureFormat::Rgba8Unorm,
+        TextureFormat::Rgba8UnormSrgb,
+        TextureFormat::Bgra8Unorm,
+        TextureFormat::Bgra8UnormSrgb,
+        TextureFormat::R16Unorm,
+        TextureFormat::Rg16Unorm,
+        TextureFormat::Rgba16Unorm,
+        TextureFormat::R16Float,
+        TextureFormat::Rg16Float,
+        TextureFormat::Rgba16Float,
+        TextureFormat::R32Float,
+        TextureFormat::Rg32Float,
+        TextureFormat::Rgba32Float,
+        TextureFormat::Rgb10a2Unorm,
+    ];
+
+    #[test]
+    fn f16_special_values() {
+        for (value, bits) in [
+            (0.0, 0x0000),
+            (-0.0, 0x8000),
+            (1.0, 0x3c00),
+            (-2.0, 0xc000),
+            (65504.0, 0x7bff),
+            // the smallest normal and the smallest and largest subnormal numbers
+            (2f32.powi(-14), 0x0400),
+            (2f32.powi(-24), 0x0001),
+            (2f32.powi(-14) - 2f32.powi(-24), 0x03ff),
+            (f32::INFINITY, 0x7c00),
+            (f32::NEG_INFINITY, 0xfc00),
+        ] {
+            assert_eq!(f32_to_f16(value), bits, "{value}");
+            assert_eq!(f16_to_f32(bits).to_bits(), value.to_bits(), "{bits:#06x}");
+        }
+    }
+
+    #[test]
+    fn f16_out_of_range() {
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert_eq!(f32_to_f16(-1e6), 0xfc00);
+        assert_eq!(f32_to_f16(1e-10), 0x0000);
+        assert_eq!(f32_to_f16(-1e-10), 0x8000);
+    }
+
+    #[test]
+    fn f16_nan() {
+        let bits = f32_to_f16(f32::NAN);
+        assert_eq!(bits & 0x7c00, 0x7c00);
+        assert_ne!(bits & 0x3ff, 0);
+        assert!(f16_to_f32(bits).is_nan());
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn f16_round_trip() {
+        // every number but NaN survives a round trip through f32
+        for bits in 0..=u16::MAX {
+            let value = f16_to_f32(bits);
+            if value.is_nan() {
+                continue;
+            }
+            assert_eq!(f32_to_f16(value), bits, "{bits:#06x} {value}");
+        }
+    }
+
+    #[test]
+    fn f16_rounds_to_nearest() {
+        for value in [0.1f32, 0.3, 3.3, 1000.7, -42.42, 1e-5] {
+            let error = (f16_to_f32(f32_to_f16(value)) - value).abs();
+            // half a unit in the last place of the 11 bit mantissa, or of the subnormal spacing
+            let tolerance = (value.abs() * 2f32.powi(-11)).max(2f32.powi(-25));
+            assert!(error <= tolerance, "{value}: {error}");
+        }
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        // the linear segment and the curve meet
+        assert!((linear_to_srgb(0.0031308) - 0.04045).abs() < 1e-4);
+        for i in 0..=100 {
+            let value = i as f32 / 100.0;
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-5);
+        }
+        // every 8 bit value survives a round trip through linear space
+        for value in 0..=255u8 {
+            let texel = [value, value, value, value];
+            let color = decode(TextureFormat::Rgba8UnormSrgb, &texel).unwrap();
+            assert_eq!(encode(TextureFormat::Rgba8UnormSrgb, color).unwrap(), texel);
+        }
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let color = Vec4::new(0.25, 0.5, 0.75, 1.0);
+        for format in FORMATS {
+            assert!(is_supported(format), "{format:?}");
+            let bytes = encode(format, color).unwrap();
+            assert_eq!(
+                bytes.len() as u32,
+                format.block_copy_size(None).unwrap(),
+                "{format:?}"
+            );
+            let decoded = decode(format, &bytes).unwrap();
+            // channels the format lacks are 0, or 1 for alpha
+            let n = channels(format);
+            let mut expected = color;
+            for i in n..3 {
+                expected[i] = 0.0;
+            }
+            if n < 4 {
+                expected[3] = 1.0;
+            }
+            // 8 bit sRGB values are coarsest near 1
+            let tolerance = if format.is_srgb() { 4e-3 } else { 2e-3 };
+            assert!(
+                (decoded - expected).abs().max_element() < tolerance,
+                "{format:?}: {decoded} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn channel_order() {
+        let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(
+            encode(TextureFormat::Rgba8Unorm, red).unwrap(),
+            [255, 0, 0, 255]
+        );
+        assert_eq!(
+            encode(TextureFormat::Bgra8Unorm, red).unwrap(),
+            [0, 0, 255, 255]
+        );
+        assert_eq!(
+            encode(TextureFormat::Rgb10a2Unorm, red).unwrap(),
+            (0x3ff | (3 << 30) as u32).to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn clamps_unorm() {
+        let color = Vec4::new(-1.0, 2.0, 0.5, 1.0);
+        assert_eq!(
+            encode(TextureFormat::Rgba8Unorm, color).unwrap(),
+            [0, 255, 128, 255]
+        );
+    }
+
+    #[test]
+    fn unsupported_formats() {
+        for format in [
+            TextureFormat::Depth32Float,
+            TextureFormat::Bc1RgbaUnorm,
+            TextureFormat::Rgba8Uint,
+        ] {
+            assert!(!is_supported(format));
+            assert!(decode(format, &[0; 16]).is_none());
+        }
+    }
+}
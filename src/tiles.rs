@@ -0,0 +1,355 @@
+#[cfg(feature = "pbr")]
+use bevy::pbr::{
+    build_directional_light_cascades, clear_directional_light_cascades, SimulationLightSystems,
+};
+use bevy::{
+    math::Vec3A,
+    prelude::*,
+    render::{
+        camera::{camera_system, CameraProjection, CameraProjectionPlugin},
+        renderer::RenderDevice,
+        view::{update_frusta, VisibilitySystems},
+    },
+    transform::TransformSystem,
+};
+
+/// Splits a render target that exceeds the device limits into equally sized tiles.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub struct TileGrid {
+    /// Number of tiles along each axis.
+    pub count: UVec2,
+    /// Size of every tile. The tiles of the last row and column may reach past the image.
+    pub tile_size: UVec2,
+}
+
+impl Default for TileGrid {
+    fn default() -> Self {
+        Self {
+            count: UVec2::ONE,
+            tile_size: UVec2::ONE,
+        }
+    }
+}
+
+impl TileGrid {
+    /// The grid with the fewest tiles whose textures stay within `max_dimension`
+    /// and whose readback buffers stay within `max_buffer_size`.
+    pub fn new(size: UVec2, texel_size: u32, max_dimension: u32, max_buffer_size: u64) -> Self {
+        let size = size.max(UVec2::ONE);
+        let max_dimension = max_dimension.max(1);
+        let mut count = (size + max_dimension - 1) / max_dimension;
+        loop {
+            let tile_size = (size + count - 1) / count;
+            let padded_bytes_per_row =
+                RenderDevice::align_copy_bytes_per_row((tile_size.x * texel_size) as usize) as u64;
+            if padded_bytes_per_row * tile_size.y as u64 <= max_buffer_size || tile_size.y == 1 {
+                return Self { count, tile_size };
+            }
+            // more rows keep the tiles as wide as possible
+            count.y += 1;
+        }
+    }
+
+    /// Number of tiles.
+    pub fn len(&self) -> usize {
+        (self.count.x * self.count.y) as usize
+    }
+
+    /// Whether the image is split into more than one tile.
+    pub fn is_tiled(&self) -> bool {
+        self.len() > 1
+    }
+
+    /// The pixels covered by a tile. Tiles are ordered row by row, starting in the top left.
+    pub fn tile_rect(&self, index: usize) -> URect {
+        let index = index as u32;
+        let min = UVec2::new(index % self.count.x, index / self.count.x) * self.tile_size;
+        URect::from_corners(min, min + self.tile_size)
+    }
+
+    /// Combines the data of the tiles into the data of an image of the given size.
+    /// Returns `None` if a tile is missing or has the wrong size.
+    pub fn stitch(&self, tiles: &[Vec<u8>], size: UVec2, texel_size: usize) -> Option<Vec<u8>> {
+        let tile_bytes = (self.tile_size.x * self.tile_size.y) as usize * texel_size;
+        if tiles.len() != self.len() || tiles.iter().any(|tile| tile.len() != tile_bytes) {
+            return None;
+        }
+
+        let mut data = vec![0; (size.x * size.y) as usize * texel_size];
+        for (index, tile) in tiles.iter().enumerate() {
+            let rect = self.tile_rect(index);
+            // crop the parts reaching past the image
+            let max = rect.max.min(size);
+            let row_bytes = max.x.saturating_sub(rect.min.x) as usize * texel_size;
+            for y in rect.min.y..max.y {
+                let source = ((y - rect.min.y) * self.tile_size.x) as usize * texel_size;
+                let target = (y * size.x + rect.min.x) as usize * texel_size;
+                data[target..target + row_bytes].copy_from_slice(&tile[source..source + row_bytes]);
+            }
+        }
+        Some(data)
+    }
+}
+
+/// A projection that renders one tile of a larger image, used by the cameras of tiled tasks.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component, Default)]
+pub struct TileProjection {
+    /// The projection of the whole image.
+    pub base: Projection,
+    /// Size of the whole image in pixels.
+    pub size: UVec2,
+    /// The pixels of the tile that is rendered.
+    pub tile: URect,
+}
+
+impl Default for TileProjection {
+    fn default() -> Self {
+        Self {
+            base: Projection::default(),
+            size: UVec2::ONE,
+            tile: URect::new(0, 0, 1, 1),
+        }
+    }
+}
+
+impl TileProjection {
+    /// The tile's corners in uv coordinates of the whole image.
+    fn tile_uv(&self) -> Rect {
+        let size = self.size.max(UVec2::ONE).as_vec2();
+        Rect::from_corners(
+            self.tile.min.as_vec2() / size,
+            self.tile.max.as_vec2() / size,
+        )
+    }
+
+    /// Maps the normalized device coordinates of the tile to the ones of the whole viewport.
+    fn tile_matrix(&self) -> Mat4 {
+        let uv = self.tile_uv();
+        // normalized device coordinates point up, uv coordinates down
+        let (left, right) = (uv.min.x * 2.0 - 1.0, uv.max.x * 2.0 - 1.0);
+        let (top, bottom) = (1.0 - uv.min.y * 2.0, 1.0 - uv.max.y * 2.0);
+        let scale = Vec2::new(2.0 / (right - left), 2.0 / (top - bottom));
+        let offset = Vec2::new(
+            -(left + right) / (right - left),
+            -(top + bottom) / (top - bottom),
+        );
+        // the offset is applied in clip space, so it is scaled by w
+        Mat4::from_cols(
+            Vec4::new(scale.x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, scale.y, 0.0, 0.0),
+            Vec4::Z,
+            Vec4::new(offset.x, offset.y, 0.0, 1.0),
+        )
+    }
+}
+
+impl CameraProjection for TileProjection {
+    fn get_projection_matrix(&self) -> Mat4 {
+        self.tile_matrix() * self.base.get_projection_matrix()
+    }
+
+    fn update(&mut self, _width: f32, _height: f32) {
+        // the viewport only shows the tile, but the projection covers the whole image
+        let size = self.size.as_vec2();
+        self.base.update(size.x, size.y);
+    }
+
+    fn far(&self) -> f32 {
+        self.base.far()
+    }
+
+    fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
+        let corners = self.base.get_frustum_corners(z_near, z_far);
+        let uv = self.tile_uv();
+        let mut tile_corners = [Vec3A::ZERO; 8];
+        for (plane, tile_plane) in corners
+            .chunks_exact(4)
+            .zip(tile_corners.chunks_exact_mut(4))
+        {
+            // bottom right, top right, top left, bottom left
+            let at = |x: f32, y: f32| {
+                let top = plane[2].lerp(plane[1], x);
+                let bottom = plane[3].lerp(plane[0], x);
+                top.lerp(bottom, y)
+            };
+            tile_plane.copy_from_slice(&[
+                at(uv.max.x, uv.max.y),
+                at(uv.max.x, uv.min.y),
+                at(uv.min.x, uv.min.y),
+                at(uv.min.x, uv.max.y),
+            ]);
+        }
+        tile_corners
+    }
+}
+
+/// Updates the cameras using a [`TileProjection`] like bevy does for its own projections.
+#[derive(Default)]
+pub struct TileProjectionPlugin;
+
+impl Plugin for TileProjectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(CameraProjectionPlugin::<TileProjection>::default())
+            .add_systems(
+                PostUpdate,
+                update_frusta::<TileProjection>
+                    .after(camera_system::<TileProjection>)
+                    .after(TransformSystem::TransformPropagate)
+                    .before(VisibilitySystems::CheckVisibility),
+            );
+
+        // the cascades of directional light shadows are fitted to the camera's frustum
+        #[cfg(feature = "pbr")]
+        app.add_systems(
+            PostUpdate,
+            build_directional_light_cascades::<TileProjection>
+                .in_set(SimulationLightSystems::UpdateDirectionalLightCascades)
+                .after(clear_directional_light_cascades),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiles of a grid whose texels hold the index of their pixel in the image,
+    /// or 255 where the tile reaches past it.
+    fn indexed_tiles(grid: &TileGrid, size: UVec2) -> Vec<Vec<u8>> {
+        (0..grid.len())
+            .map(|index| {
+                let rect = grid.tile_rect(index);
+                (rect.min.y..rect.max.y)
+                    .flat_map(|y| (rect.min.x..rect.max.x).map(move |x| (x, y)))
+                    .map(|(x, y)| {
+                        if x < size.x && y < size.y {
+                            (y * size.x + x) as u8
+                        } else {
+                            255
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grid_within_limits() {
+        let size = UVec2::new(100, 50);
+        let grid = TileGrid::new(size, 4, 256, u64::MAX);
+        assert_eq!(grid.count, UVec2::ONE);
+        assert_eq!(grid.tile_size, size);
+        assert!(!grid.is_tiled());
+        assert_eq!(grid.tile_rect(0), URect::from_corners(UVec2::ZERO, size));
+    }
+
+    #[test]
+    fn grid_with_one_row() {
+        let grid = TileGrid::new(UVec2::new(1000, 100), 4, 256, u64::MAX);
+        assert_eq!(grid.count, UVec2::new(4, 1));
+        assert_eq!(grid.tile_size, UVec2::new(250, 100));
+        assert_eq!(grid.tile_rect(3), URect::new(750, 0, 1000, 100));
+    }
+
+    #[test]
+    fn buffer_limit_adds_rows() {
+        // 64 texels of 4 bytes fill one aligned row of 256 bytes
+        let grid = TileGrid::new(UVec2::splat(64), 4, 1024, 256 * 16);
+        assert_eq!(grid.count, UVec2::new(1, 4));
+        assert_eq!(grid.tile_size, UVec2::new(64, 16));
+
+        // a single row is the smallest tile, even if it exceeds the limit
+        let grid = TileGrid::new(UVec2::new(64, 2), 4, 1024, 1);
+        assert_eq!(grid.tile_size, UVec2::new(64, 1));
+    }
+
+    #[test]
+    fn edge_tiles_reach_past_the_image() {
+        let grid = TileGrid::new(UVec2::new(10, 7), 1, 4, u64::MAX);
+        assert_eq!(grid.count, UVec2::new(3, 2));
+        assert_eq!(grid.tile_size, UVec2::new(4, 4));
+        // row by row, starting in the top left
+        assert_eq!(grid.tile_rect(1), URect::new(4, 0, 8, 4));
+        assert_eq!(grid.tile_rect(2), URect::new(8, 0, 12, 4));
+        assert_eq!(grid.tile_rect(3), URect::new(0, 4, 4, 8));
+        assert_eq!(grid.tile_rect(5), URect::new(8, 4, 12, 8));
+    }
+
+    #[test]
+    fn stitch_places_tiles_in_order() {
+        for (size, max_dimension) in [
+            (UVec2::new(10, 7), 4),
+            (UVec2::new(12, 3), 5),
+            (UVec2::new(9, 9), 3),
+        ] {
+            let grid = TileGrid::new(size, 1, max_dimension, u64::MAX);
+            let tiles = indexed_tiles(&grid, size);
+            let expected: Vec<u8> = (0..size.x * size.y).map(|i| i as u8).collect();
+            assert_eq!(grid.stitch(&tiles, size, 1), Some(expected), "{size}");
+        }
+    }
+
+    #[test]
+    fn stitch_keeps_texels_together() {
+        let size = UVec2::new(3, 2);
+        let grid = TileGrid::new(size, 2, 2, u64::MAX);
+        let tiles: Vec<Vec<u8>> = indexed_tiles(&grid, size)
+            .into_iter()
+            .map(|tile| tile.into_iter().flat_map(|i| [i, !i]).collect())
+            .collect();
+        let expected: Vec<u8> = (0..6u8).flat_map(|i| [i, !i]).collect();
+        assert_eq!(grid.stitch(&tiles, size, 2), Some(expected));
+    }
+
+    #[test]
+    fn stitch_rejects_missing_tiles() {
+        let size = UVec2::new(10, 7);
+        let grid = TileGrid::new(size, 1, 4, u64::MAX);
+        let mut tiles = indexed_tiles(&grid, size);
+        tiles[4].pop();
+        assert_eq!(grid.stitch(&tiles, size, 1), None);
+        tiles.pop();
+        assert_eq!(grid.stitch(&tiles, size, 1), None);
+    }
+
+    fn projection(tile: URect) -> TileProjection {
+        TileProjection {
+            size: UVec2::new(4, 2),
+            tile,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn tile_matrix_of_the_whole_image() {
+        let matrix = projection(URect::new(0, 0, 4, 2)).tile_matrix();
+        assert!(matrix.abs_diff_eq(Mat4::IDENTITY, 1e-6));
+    }
+
+    #[test]
+    fn tile_matrix_maps_the_tile_to_the_viewport() {
+        // the top left quarter of the image covers x in -1..0 and y in 0..1
+        let matrix = projection(URect::new(0, 0, 2, 1)).tile_matrix();
+        for (ndc, expected) in [
+            (Vec2::new(-1.0, 1.0), Vec2::new(-1.0, 1.0)),
+            (Vec2::new(0.0, 0.0), Vec2::new(1.0, -1.0)),
+            (Vec2::new(-0.5, 0.5), Vec2::ZERO),
+        ] {
+            // points in clip space are scaled by w
+            let w = 2.0;
+            let clip = matrix * (ndc * w).extend(0.3).extend(w);
+            assert!(
+                (clip.truncate().truncate() / clip.w).abs_diff_eq(expected, 1e-6),
+                "{ndc} -> {clip}"
+            );
+            assert_eq!(clip.z, 0.3);
+            assert_eq!(clip.w, w);
+        }
+
+        // the bottom right tile
+        let matrix = projection(URect::new(2, 1, 4, 2)).tile_matrix();
+        let clip = matrix * Vec4::new(0.5, -0.5, 0.0, 1.0);
+        assert!(clip.abs_diff_eq(Vec4::new(0.0, 0.0, 0.0, 1.0), 1e-6));
+    }
+}